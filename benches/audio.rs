@@ -0,0 +1,47 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use telnyx_ai_service::audio::{self, g711, resample, wav};
+
+fn one_second_pcm(rate: u32) -> Vec<i16> {
+    (0..rate)
+        .map(|i| ((i as f64 * 440.0 * 2.0 * std::f64::consts::PI / rate as f64).sin() * 10000.0) as i16)
+        .collect()
+}
+
+fn g711_benches(c: &mut Criterion) {
+    let pcm = one_second_pcm(8000);
+    let ulaw = g711::encode_ulaw(&pcm);
+    let alaw = g711::encode_alaw(&pcm);
+
+    c.bench_function("ulaw_encode_1s_8k", |b| b.iter(|| g711::encode_ulaw(black_box(&pcm))));
+    c.bench_function("ulaw_decode_1s_8k", |b| b.iter(|| g711::decode_ulaw(black_box(&ulaw))));
+    c.bench_function("alaw_encode_1s_8k", |b| b.iter(|| g711::encode_alaw(black_box(&pcm))));
+    c.bench_function("alaw_decode_1s_8k", |b| b.iter(|| g711::decode_alaw(black_box(&alaw))));
+}
+
+fn resample_benches(c: &mut Criterion) {
+    let pcm_24k = one_second_pcm(24000);
+    let pcm_8k = one_second_pcm(8000);
+
+    c.bench_function("resample_1s_24k_to_8k", |b| {
+        b.iter(|| resample::resample(black_box(&pcm_24k), 24000, 8000).unwrap())
+    });
+    c.bench_function("resample_1s_8k_to_16k", |b| {
+        b.iter(|| resample::resample(black_box(&pcm_8k), 8000, 16000).unwrap())
+    });
+    c.bench_function("pcm16_24k_to_ulaw_1s", |b| {
+        b.iter(|| audio::pcm16_to_ulaw(black_box(&pcm_24k), 24000).unwrap())
+    });
+}
+
+fn wav_benches(c: &mut Criterion) {
+    let ulaw = g711::encode_ulaw(&one_second_pcm(8000));
+    let file = wav::ulaw_to_wav(&ulaw);
+
+    c.bench_function("wav_write_ulaw_1s", |b| b.iter(|| wav::ulaw_to_wav(black_box(&ulaw))));
+    c.bench_function("wav_read_ulaw_1s_to_pcm", |b| {
+        b.iter(|| wav::read_wav(black_box(&file)).unwrap().to_pcm16())
+    });
+}
+
+criterion_group!(benches, g711_benches, resample_benches, wav_benches);
+criterion_main!(benches);
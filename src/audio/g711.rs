@@ -0,0 +1,170 @@
+//! G.711 μ-law / A-law (ITU-T G.711), el formato PCMU/PCMA de Telnyx Media Streams.
+
+const ULAW_BIAS: i32 = 0x84;
+const ULAW_CLIP: i32 = 32635;
+
+/// Tabla de decodificación μ-law precalculada (256 códigos)
+static ULAW_TABLE: [i16; 256] = build_ulaw_table();
+/// Tabla de decodificación A-law precalculada (256 códigos)
+static ALAW_TABLE: [i16; 256] = build_alaw_table();
+
+const fn build_ulaw_table() -> [i16; 256] {
+    let mut table = [0i16; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = ulaw_decode_sample(i as u8);
+        i += 1;
+    }
+    table
+}
+
+const fn build_alaw_table() -> [i16; 256] {
+    let mut table = [0i16; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = alaw_decode_sample(i as u8);
+        i += 1;
+    }
+    table
+}
+
+const fn ulaw_decode_sample(code: u8) -> i16 {
+    let code = !code;
+    let exponent = ((code >> 4) & 0x07) as i32;
+    let mantissa = (code & 0x0F) as i32;
+    let magnitude = (((mantissa << 3) + ULAW_BIAS) << exponent) - ULAW_BIAS;
+    if code & 0x80 != 0 {
+        -magnitude as i16
+    } else {
+        magnitude as i16
+    }
+}
+
+const fn alaw_decode_sample(code: u8) -> i16 {
+    let code = code ^ 0x55;
+    let mut magnitude = ((code & 0x0F) as i32) << 4;
+    let segment = ((code & 0x70) >> 4) as i32;
+    match segment {
+        0 => magnitude += 8,
+        1 => magnitude += 0x108,
+        _ => {
+            magnitude += 0x108;
+            magnitude <<= segment - 1;
+        }
+    }
+    if code & 0x80 != 0 {
+        magnitude as i16
+    } else {
+        -magnitude as i16
+    }
+}
+
+/// PCM16 lineal -> código μ-law
+pub fn linear_to_ulaw(sample: i16) -> u8 {
+    let mut pcm = sample as i32;
+    let sign = if pcm < 0 {
+        pcm = -pcm;
+        0x80
+    } else {
+        0x00
+    };
+    pcm = pcm.min(ULAW_CLIP) + ULAW_BIAS;
+
+    let mut exponent = 7;
+    let mut mask = 0x4000;
+    while exponent > 0 && pcm & mask == 0 {
+        exponent -= 1;
+        mask >>= 1;
+    }
+    let mantissa = (pcm >> (exponent + 3)) & 0x0F;
+
+    !(sign | (exponent << 4) | mantissa) as u8
+}
+
+/// Código μ-law -> PCM16 lineal
+pub fn ulaw_to_linear(code: u8) -> i16 {
+    ULAW_TABLE[code as usize]
+}
+
+/// PCM16 lineal -> código A-law
+pub fn linear_to_alaw(sample: i16) -> u8 {
+    // A-law trabaja con 13 bits de magnitud
+    let mut pcm = (sample as i32) >> 3;
+    let mask = if pcm >= 0 {
+        0xD5
+    } else {
+        pcm = -pcm - 1;
+        0x55
+    };
+
+    let mut segment = 0;
+    while segment < 8 && pcm > (0x20 << segment) - 1 {
+        segment += 1;
+    }
+    if segment >= 8 {
+        return (0x7F ^ mask) as u8;
+    }
+
+    let mut code = segment << 4;
+    code |= if segment < 2 { (pcm >> 1) & 0x0F } else { (pcm >> segment) & 0x0F };
+    (code ^ mask) as u8
+}
+
+/// Código A-law -> PCM16 lineal
+pub fn alaw_to_linear(code: u8) -> i16 {
+    ALAW_TABLE[code as usize]
+}
+
+pub fn encode_ulaw(samples: &[i16]) -> Vec<u8> {
+    samples.iter().map(|&s| linear_to_ulaw(s)).collect()
+}
+
+pub fn decode_ulaw(codes: &[u8]) -> Vec<i16> {
+    codes.iter().map(|&c| ulaw_to_linear(c)).collect()
+}
+
+pub fn encode_alaw(samples: &[i16]) -> Vec<u8> {
+    samples.iter().map(|&s| linear_to_alaw(s)).collect()
+}
+
+pub fn decode_alaw(codes: &[u8]) -> Vec<i16> {
+    codes.iter().map(|&c| alaw_to_linear(c)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ulaw_codes_round_trip() {
+        for code in 0..=255u8 {
+            let expected = if code == 0x7F { 0xFF } else { code }; // ±0 comparten representación
+            assert_eq!(linear_to_ulaw(ulaw_to_linear(code)), expected, "code {:#04x}", code);
+        }
+    }
+
+    #[test]
+    fn alaw_codes_round_trip() {
+        for code in 0..=255u8 {
+            assert_eq!(linear_to_alaw(alaw_to_linear(code)), code, "code {:#04x}", code);
+        }
+    }
+
+    #[test]
+    fn quantization_error_is_bounded() {
+        for sample in (i16::MIN..=i16::MAX).step_by(7) {
+            let ulaw = ulaw_to_linear(linear_to_ulaw(sample)) as i32;
+            let alaw = alaw_to_linear(linear_to_alaw(sample)) as i32;
+            // Compansión logarítmica: el error crece con la amplitud (~1/16 del valor)
+            let tolerance = (sample as i32).abs() / 16 + 16;
+            assert!((ulaw - sample as i32).abs() <= tolerance, "μ-law {} -> {}", sample, ulaw);
+            assert!((alaw - sample as i32).abs() <= tolerance, "A-law {} -> {}", sample, alaw);
+        }
+    }
+
+    #[test]
+    fn silence_encodes_to_standard_codes() {
+        assert_eq!(linear_to_ulaw(0), 0xFF);
+        assert_eq!(linear_to_alaw(0), 0xD5);
+    }
+}
@@ -0,0 +1,51 @@
+//! Utilidades de audio para telefonía: G.711 μ-law/A-law, remuestreo PCM16,
+//! WAV, decodificación MP3 y VAD por energía. Todo en memoria, sin dependencias del servidor.
+
+pub mod g711;
+pub mod mp3;
+pub mod resample;
+pub mod vad;
+pub mod wav;
+
+/// Tasa de muestreo de Telnyx Media Streams (PCMU/PCMA)
+pub const TELEPHONY_SAMPLE_RATE: u32 = 8000;
+
+/// PCM16 mono a cualquier tasa soportada -> μ-law 8 kHz
+pub fn pcm16_to_ulaw(samples: &[i16], sample_rate: u32) -> anyhow::Result<Vec<u8>> {
+    let resampled = resample::resample(samples, sample_rate, TELEPHONY_SAMPLE_RATE)?;
+    Ok(g711::encode_ulaw(&resampled))
+}
+
+/// PCM16 mono a cualquier tasa soportada -> A-law 8 kHz
+pub fn pcm16_to_alaw(samples: &[i16], sample_rate: u32) -> anyhow::Result<Vec<u8>> {
+    let resampled = resample::resample(samples, sample_rate, TELEPHONY_SAMPLE_RATE)?;
+    Ok(g711::encode_alaw(&resampled))
+}
+
+/// MP3 (p. ej. de ElevenLabs) -> μ-law 8 kHz listo para inyectar en el media stream
+pub fn mp3_to_ulaw(mp3: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let decoded = mp3::decode_mp3(mp3)?;
+    pcm16_to_ulaw(&decoded.samples, decoded.sample_rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pcm_to_ulaw_downsamples_to_telephony_rate() {
+        let pcm: Vec<i16> = (0..24000).map(|i| ((i % 100) * 100) as i16).collect();
+        let ulaw = pcm16_to_ulaw(&pcm, 24000).unwrap();
+        assert_eq!(ulaw.len(), 8000);
+        let back = g711::decode_ulaw(&ulaw);
+        assert_eq!(back.len(), 8000);
+    }
+
+    #[test]
+    fn mp3_48k_fixture_converts_to_telephony_rate() {
+        let mp3 = include_bytes!("../../tests/fixtures/silence_48k_mono.mp3");
+        let samples = mp3::decode_mp3(mp3).unwrap().samples.len();
+        let ulaw = mp3_to_ulaw(mp3).unwrap();
+        assert_eq!(ulaw.len(), samples * 8000 / 48000);
+    }
+}
@@ -0,0 +1,91 @@
+//! Decodificación MP3 (salida de ElevenLabs) a PCM16 mono.
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+#[derive(Debug, Clone)]
+pub struct DecodedAudio {
+    /// PCM16 mono (los canales se promedian)
+    pub samples: Vec<i16>,
+    pub sample_rate: u32,
+}
+
+pub fn decode_mp3(bytes: &[u8]) -> anyhow::Result<DecodedAudio> {
+    let source = MediaSourceStream::new(Box::new(std::io::Cursor::new(bytes.to_vec())), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension("mp3");
+
+    let probed = symphonia::default::get_probe()
+        .format(&hint, source, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| anyhow::anyhow!("MP3 inválido: {}", e))?;
+    let mut format = probed.format;
+
+    let track = format.default_track()
+        .ok_or_else(|| anyhow::anyhow!("MP3 sin pista de audio"))?;
+    let track_id = track.id;
+    let mut sample_rate = track.codec_params.sample_rate.unwrap_or(0);
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| anyhow::anyhow!("Decoder MP3 no disponible: {}", e))?;
+
+    let mut samples = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(anyhow::anyhow!("Error leyendo MP3: {}", e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // Frames corruptos sueltos: se saltan, como hacen los reproductores
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(anyhow::anyhow!("Error decodificando MP3: {}", e)),
+        };
+
+        let spec = *decoded.spec();
+        sample_rate = spec.rate;
+        let channels = spec.channels.count().max(1);
+        let mut buffer = SampleBuffer::<i16>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+
+        samples.extend(buffer.samples().chunks(channels).map(|frame| {
+            (frame.iter().map(|&s| s as i32).sum::<i32>() / frame.len() as i32) as i16
+        }));
+    }
+
+    if sample_rate == 0 {
+        return Err(anyhow::anyhow!("MP3 sin frames decodificables"));
+    }
+
+    Ok(DecodedAudio { samples, sample_rate })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 10 frames MPEG-1 Layer III de silencio: 48 kHz, mono, 32 kbps (96 bytes y 1152 muestras por frame)
+    const SILENCE_48K_MONO: &[u8] = include_bytes!("../../tests/fixtures/silence_48k_mono.mp3");
+
+    #[test]
+    fn decodes_fixture_rate_and_length() {
+        let decoded = decode_mp3(SILENCE_48K_MONO).unwrap();
+        assert_eq!(decoded.sample_rate, 48000);
+        assert_eq!(decoded.samples.len(), 10 * 1152);
+        assert!(decoded.samples.iter().all(|&s| s == 0));
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(decode_mp3(&[0u8; 64]).is_err());
+    }
+}
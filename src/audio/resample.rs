@@ -0,0 +1,122 @@
+//! Remuestreo PCM16 mono entre tasas arbitrarias: 8 kHz (telefonía), 16 kHz (STT),
+//! 22.05/24 kHz (TTS) y 32/44.1/48 kHz (MP3 habituales).
+
+/// Rango de tasas aceptadas (Hz)
+pub const SUPPORTED_RATES: std::ops::RangeInclusive<u32> = 8000..=192_000;
+
+pub fn is_supported_rate(rate: u32) -> bool {
+    SUPPORTED_RATES.contains(&rate)
+}
+
+/// Remuestrea con interpolación lineal. Al bajar de tasa aplica antes un promedio móvil
+/// como filtro paso-bajo para reducir aliasing (suficiente para voz telefónica).
+pub fn resample(samples: &[i16], from_rate: u32, to_rate: u32) -> anyhow::Result<Vec<i16>> {
+    if !is_supported_rate(from_rate) || !is_supported_rate(to_rate) {
+        return Err(anyhow::anyhow!(
+            "Tasa de muestreo no soportada: {} -> {} (soportadas: {:?})",
+            from_rate, to_rate, SUPPORTED_RATES
+        ));
+    }
+    if from_rate == to_rate || samples.is_empty() {
+        return Ok(samples.to_vec());
+    }
+
+    let filtered;
+    let input = if to_rate < from_rate {
+        let window = (from_rate as f64 / to_rate as f64).round() as usize;
+        filtered = low_pass(samples, window);
+        &filtered[..]
+    } else {
+        samples
+    };
+
+    let out_len = (input.len() as u64 * to_rate as u64 / from_rate as u64) as usize;
+    let step = from_rate as f64 / to_rate as f64;
+    let last = input.len() - 1;
+
+    let output = (0..out_len)
+        .map(|i| {
+            let position = i as f64 * step;
+            let index = position.floor() as usize;
+            let frac = position - index as f64;
+            let a = input[index.min(last)] as f64;
+            let b = input[(index + 1).min(last)] as f64;
+            (a + (b - a) * frac).round().clamp(i16::MIN as f64, i16::MAX as f64) as i16
+        })
+        .collect();
+
+    Ok(output)
+}
+
+fn low_pass(samples: &[i16], window: usize) -> Vec<i16> {
+    if window <= 1 {
+        return samples.to_vec();
+    }
+
+    let half = window / 2;
+    let mut prefix = Vec::with_capacity(samples.len() + 1);
+    prefix.push(0i64);
+    for &s in samples {
+        prefix.push(prefix[prefix.len() - 1] + s as i64);
+    }
+
+    (0..samples.len())
+        .map(|i| {
+            let start = i.saturating_sub(half);
+            let end = (i + window - half).min(samples.len());
+            ((prefix[end] - prefix[start]) / (end - start) as i64) as i16
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f64, rate: u32, millis: u32) -> Vec<i16> {
+        let n = rate as usize * millis as usize / 1000;
+        (0..n)
+            .map(|i| ((i as f64 * freq * 2.0 * std::f64::consts::PI / rate as f64).sin() * 8000.0) as i16)
+            .collect()
+    }
+
+    #[test]
+    fn output_length_follows_rate_ratio() {
+        let input = sine(440.0, 24000, 1000);
+        assert_eq!(resample(&input, 24000, 8000).unwrap().len(), 8000);
+        assert_eq!(resample(&input, 24000, 22050).unwrap().len(), 22050);
+        assert_eq!(resample(&sine(440.0, 8000, 1000), 8000, 16000).unwrap().len(), 16000);
+    }
+
+    #[test]
+    fn upsample_then_downsample_round_trips() {
+        let original = sine(300.0, 8000, 200);
+        for rate in [16000, 22050, 24000] {
+            let up = resample(&original, 8000, rate).unwrap();
+            let back = resample(&up, rate, 8000).unwrap();
+            assert_eq!(back.len(), original.len());
+            // Ignorar los bordes, donde el filtro no tiene ventana completa
+            let max_error = original[4..original.len() - 4]
+                .iter()
+                .zip(&back[4..back.len() - 4])
+                .map(|(a, b)| (*a as i32 - *b as i32).abs())
+                .max()
+                .unwrap();
+            assert!(max_error < 800, "8k -> {} -> 8k error {}", rate, max_error);
+        }
+    }
+
+    #[test]
+    fn downsamples_common_mp3_rates() {
+        for rate in [32000, 44100, 48000] {
+            let input = sine(440.0, rate, 1000);
+            assert_eq!(resample(&input, rate, 8000).unwrap().len(), 8000, "{} -> 8k", rate);
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_rates() {
+        assert!(resample(&[0, 1, 2], 0, 8000).is_err());
+        assert!(resample(&[0, 1, 2], 8000, 400_000).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+//! Detección de actividad de voz por energía, pensada para frames μ-law de 20 ms.
+//! No distingue voz de ruido fuerte; basta para silencios y barge-in.
+
+use super::TELEPHONY_SAMPLE_RATE;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VadConfig {
+    /// Nivel (dBFS) por encima del cual un frame cuenta como voz
+    pub threshold_dbfs: f64,
+    /// Voz continua necesaria para declarar inicio de habla
+    pub speech_start_ms: u32,
+    /// Silencio continuo necesario para declarar fin de habla
+    pub hangover_ms: u32,
+    pub sample_rate: u32,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            threshold_dbfs: -35.0,
+            speech_start_ms: 60,
+            hangover_ms: 400,
+            sample_rate: TELEPHONY_SAMPLE_RATE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VadEvent {
+    SpeechStart,
+    SpeechEnd,
+}
+
+#[derive(Debug)]
+pub struct EnergyVad {
+    config: VadConfig,
+    speaking: bool,
+    /// Milisegundos acumulados en el estado contrario al actual
+    run_ms: f64,
+}
+
+impl EnergyVad {
+    pub fn new(config: VadConfig) -> Self {
+        Self { config, speaking: false, run_ms: 0.0 }
+    }
+
+    pub fn is_speaking(&self) -> bool {
+        self.speaking
+    }
+
+    /// Procesa un frame PCM16; devuelve un evento cuando cambia el estado
+    pub fn process(&mut self, samples: &[i16]) -> Option<VadEvent> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let frame_ms = samples.len() as f64 * 1000.0 / self.config.sample_rate as f64;
+        let voiced = rms_dbfs(samples) >= self.config.threshold_dbfs;
+
+        if voiced == self.speaking {
+            self.run_ms = 0.0;
+            return None;
+        }
+
+        self.run_ms += frame_ms;
+        let needed = if self.speaking { self.config.hangover_ms } else { self.config.speech_start_ms };
+        if self.run_ms < needed as f64 {
+            return None;
+        }
+
+        self.speaking = voiced;
+        self.run_ms = 0.0;
+        Some(if voiced { VadEvent::SpeechStart } else { VadEvent::SpeechEnd })
+    }
+
+    /// Igual que `process` sobre bytes μ-law tal como llegan del media stream
+    pub fn process_ulaw(&mut self, ulaw: &[u8]) -> Option<VadEvent> {
+        self.process(&super::g711::decode_ulaw(ulaw))
+    }
+}
+
+/// Nivel RMS en dBFS (0 = escala completa); -inf para silencio digital
+pub fn rms_dbfs(samples: &[i16]) -> f64 {
+    if samples.is_empty() {
+        return f64::NEG_INFINITY;
+    }
+    let sum: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    let rms = (sum / samples.len() as f64).sqrt();
+    20.0 * (rms / i16::MAX as f64).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(amplitude: i16) -> Vec<i16> {
+        // 20 ms de onda cuadrada a 8 kHz
+        (0..160).map(|i| if i % 2 == 0 { amplitude } else { -amplitude }).collect()
+    }
+
+    #[test]
+    fn detects_speech_start_and_end_with_hangover() {
+        let mut vad = EnergyVad::new(VadConfig::default());
+        let loud = frame(8000);
+        let quiet = frame(50);
+
+        assert_eq!(vad.process(&quiet), None);
+        assert_eq!(vad.process(&loud), None);
+        assert_eq!(vad.process(&loud), None);
+        assert_eq!(vad.process(&loud), Some(VadEvent::SpeechStart));
+        assert!(vad.is_speaking());
+
+        // Una pausa corta no corta el habla
+        for _ in 0..5 {
+            assert_eq!(vad.process(&quiet), None);
+        }
+        assert_eq!(vad.process(&loud), None);
+
+        let ended = (0..20).filter_map(|_| vad.process(&quiet)).collect::<Vec<_>>();
+        assert_eq!(ended, vec![VadEvent::SpeechEnd]);
+    }
+
+    #[test]
+    fn rms_of_full_scale_is_near_zero_dbfs() {
+        assert!(rms_dbfs(&frame(i16::MAX)).abs() < 0.01);
+        assert!(rms_dbfs(&[0; 160]).is_infinite());
+    }
+}
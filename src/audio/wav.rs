@@ -0,0 +1,177 @@
+//! Lectura/escritura WAV (RIFF) para PCM16 y G.711 μ-law/A-law.
+
+use super::g711;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WavFormat {
+    Pcm16,
+    MuLaw,
+    ALaw,
+}
+
+impl WavFormat {
+    fn format_tag(self) -> u16 {
+        match self {
+            WavFormat::Pcm16 => 1,
+            WavFormat::ALaw => 6,
+            WavFormat::MuLaw => 7,
+        }
+    }
+
+    fn bits_per_sample(self) -> u16 {
+        match self {
+            WavFormat::Pcm16 => 16,
+            WavFormat::MuLaw | WavFormat::ALaw => 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WavSpec {
+    pub format: WavFormat,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct WavAudio {
+    pub spec: WavSpec,
+    /// Bytes del chunk `data` tal cual (PCM16 little-endian o códigos G.711)
+    pub data: Vec<u8>,
+}
+
+impl WavAudio {
+    /// Muestras PCM16 intercaladas, decodificando G.711 si hace falta
+    pub fn to_pcm16(&self) -> Vec<i16> {
+        match self.spec.format {
+            WavFormat::Pcm16 => self.data
+                .chunks_exact(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]))
+                .collect(),
+            WavFormat::MuLaw => g711::decode_ulaw(&self.data),
+            WavFormat::ALaw => g711::decode_alaw(&self.data),
+        }
+    }
+}
+
+/// Serializa `data` (ya codificado según `spec.format`) como archivo WAV
+pub fn write_wav(spec: WavSpec, data: &[u8]) -> Vec<u8> {
+    let bits = spec.format.bits_per_sample();
+    let block_align = spec.channels * bits / 8;
+    let byte_rate = spec.sample_rate * block_align as u32;
+    // Los formatos no-PCM llevan cbSize (fmt de 18 bytes)
+    let fmt_len: u32 = if spec.format == WavFormat::Pcm16 { 16 } else { 18 };
+    let pad = data.len() % 2;
+
+    let mut out = Vec::with_capacity(12 + 8 + fmt_len as usize + 8 + data.len() + pad);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(4 + 8 + fmt_len + 8 + (data.len() + pad) as u32).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&fmt_len.to_le_bytes());
+    out.extend_from_slice(&spec.format.format_tag().to_le_bytes());
+    out.extend_from_slice(&spec.channels.to_le_bytes());
+    out.extend_from_slice(&spec.sample_rate.to_le_bytes());
+    out.extend_from_slice(&byte_rate.to_le_bytes());
+    out.extend_from_slice(&block_align.to_le_bytes());
+    out.extend_from_slice(&bits.to_le_bytes());
+    if fmt_len == 18 {
+        out.extend_from_slice(&0u16.to_le_bytes());
+    }
+
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if pad == 1 {
+        out.push(0);
+    }
+    out
+}
+
+/// WAV PCM16 mono a partir de muestras lineales
+pub fn pcm16_to_wav(samples: &[i16], sample_rate: u32) -> Vec<u8> {
+    let data: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+    write_wav(WavSpec { format: WavFormat::Pcm16, sample_rate, channels: 1 }, &data)
+}
+
+/// WAV μ-law 8 kHz mono tal como llega de Telnyx (sin transcodificar)
+pub fn ulaw_to_wav(codes: &[u8]) -> Vec<u8> {
+    write_wav(WavSpec { format: WavFormat::MuLaw, sample_rate: 8000, channels: 1 }, codes)
+}
+
+pub fn read_wav(bytes: &[u8]) -> anyhow::Result<WavAudio> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(anyhow::anyhow!("No es un archivo WAV (cabecera RIFF/WAVE ausente)"));
+    }
+
+    let mut spec = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let len = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into()?) as usize;
+        let body_start = offset + 8;
+        let body_end = body_start
+            .checked_add(len)
+            .filter(|end| *end <= bytes.len())
+            .ok_or_else(|| anyhow::anyhow!("Chunk WAV truncado"))?;
+        let body = &bytes[body_start..body_end];
+
+        match id {
+            b"fmt " => {
+                if body.len() < 16 {
+                    return Err(anyhow::anyhow!("Chunk fmt demasiado corto"));
+                }
+                let tag = u16::from_le_bytes([body[0], body[1]]);
+                let channels = u16::from_le_bytes([body[2], body[3]]);
+                let sample_rate = u32::from_le_bytes(body[4..8].try_into()?);
+                let bits = u16::from_le_bytes([body[14], body[15]]);
+                let format = match (tag, bits) {
+                    (1, 16) => WavFormat::Pcm16,
+                    (6, 8) => WavFormat::ALaw,
+                    (7, 8) => WavFormat::MuLaw,
+                    _ => return Err(anyhow::anyhow!("Formato WAV no soportado (tag {}, {} bits)", tag, bits)),
+                };
+                spec = Some(WavSpec { format, sample_rate, channels });
+            }
+            b"data" => {
+                let spec = spec.ok_or_else(|| anyhow::anyhow!("Chunk data antes de fmt"))?;
+                return Ok(WavAudio { spec, data: body.to_vec() });
+            }
+            _ => {}
+        }
+
+        // Los chunks se alinean a 2 bytes
+        offset = body_end + (len % 2);
+    }
+
+    Err(anyhow::anyhow!("WAV sin chunk data"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pcm16_wav_round_trip() {
+        let samples: Vec<i16> = (0..1000).map(|i| ((i * 37) % 65536 - 32768) as i16).collect();
+        let audio = read_wav(&pcm16_to_wav(&samples, 16000)).unwrap();
+        assert_eq!(audio.spec, WavSpec { format: WavFormat::Pcm16, sample_rate: 16000, channels: 1 });
+        assert_eq!(audio.to_pcm16(), samples);
+    }
+
+    #[test]
+    fn ulaw_wav_round_trip() {
+        let codes: Vec<u8> = (0..=255u8).collect();
+        let bytes = ulaw_to_wav(&codes[..255]); // longitud impar -> byte de relleno
+        let audio = read_wav(&bytes).unwrap();
+        assert_eq!(audio.spec.format, WavFormat::MuLaw);
+        assert_eq!(audio.data, &codes[..255]);
+        assert_eq!(audio.to_pcm16(), g711::decode_ulaw(&codes[..255]));
+    }
+
+    #[test]
+    fn rejects_non_wav() {
+        assert!(read_wav(b"ID3\x03\x00\x00\x00").is_err());
+    }
+}
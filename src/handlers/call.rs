@@ -1,48 +1,23 @@
 use axum::{
-    extract::{State, Json},
-    http::StatusCode,
+    extract::{Path, Query, State, Json},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
 };
 use std::sync::Arc;
 use crate::{
-    models::{InitiateCallRequest, BatchCallsRequest, CallResponse, StatsResponse, ErrorResponse},
-    services::AppState,
+    models::{InitiateCallRequest, BatchCallsRequest, CallDetail, CallDetailRecord, CallDirection, CallList, CallResponse, CallState, CallSummary, CallTranscript, CallsQuery, CdrQuery, StatsResponse, ErrorResponse, TranscriptQuery},
+    services::{AppState, cdr},
 };
 
 pub async fn initiate_call(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<InitiateCallRequest>,
 ) -> Result<(StatusCode, Json<CallResponse>), (StatusCode, Json<ErrorResponse>)> {
-    // Por defecto usar WebSocket Media Streams para latencia óptima
-    let use_websocket = std::env::var("USE_MEDIA_STREAMS")
-        .unwrap_or_else(|_| "true".to_string())
-        .parse::<bool>()
-        .unwrap_or(true);
-
-    let result = if use_websocket {
-        state.telnyx_service
-            .initiate_call_with_stream(
-                &payload.telefono,
-                &payload.nombre,
-                &payload.telefono,
-                payload.contexto.as_deref(),
-            )
-            .await
-    } else {
-        state.telnyx_service
-            .initiate_call(
-                &payload.telefono,
-                &payload.nombre,
-                &payload.telefono,
-                payload.contexto.as_deref(),
-            )
-            .await
-    };
+    reject_if_draining(&state)?;
+    validate_voice(&payload)?;
 
-    match result {
-        Ok(response) => {
-            state.total_calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-            Ok((StatusCode::CREATED, Json(response)))
-        }
+    match state.dial(&payload).await {
+        Ok(response) => Ok((StatusCode::CREATED, Json(response))),
         Err(e) => {
             Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -58,21 +33,14 @@ pub async fn initiate_call(
 pub async fn batch_calls(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<BatchCallsRequest>,
-) -> (StatusCode, Json<serde_json::Value>) {
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, Json<ErrorResponse>)> {
+    reject_if_draining(&state)?;
+    payload.calls.iter().try_for_each(validate_voice)?;
     let mut responses = Vec::new();
 
     for call_req in payload.calls {
-        match state.telnyx_service
-            .initiate_call(
-                &call_req.telefono,
-                &call_req.nombre,
-                &call_req.telefono,
-                call_req.contexto.as_deref(),
-            )
-            .await
-        {
+        match state.dial(&call_req).await {
             Ok(response) => {
-                state.total_calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
                 responses.push(serde_json::json!({
                     "status": "success",
                     "call_control_id": response.call_control_id,
@@ -89,13 +57,43 @@ pub async fn batch_calls(
         }
     }
 
-    (
+    Ok((
         StatusCode::OK,
         Json(serde_json::json!({
             "total": responses.len(),
             "results": responses
         })),
-    )
+    ))
+}
+
+/// Durante el apagado no se marcan llamadas nuevas: el balanceador debe mandarlas a otra réplica
+pub fn reject_if_draining(state: &AppState) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    if !state.lifecycle.is_draining() {
+        return Ok(());
+    }
+    Err((
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(ErrorResponse {
+            error: "Service draining".to_string(),
+            message: Some("This instance is shutting down and not accepting new calls".to_string()),
+        }),
+    ))
+}
+
+/// Rechaza la llamada antes de marcar si su perfil de voz trae ajustes fuera de rango
+pub fn validate_voice(request: &InitiateCallRequest) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let Some(voz) = &request.voz else {
+        return Ok(());
+    };
+    voz.validate().map_err(|message| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Invalid voice profile".to_string(),
+                message: Some(format!("{}: {}", request.telefono, message)),
+            }),
+        )
+    })
 }
 
 pub async fn session_stats(
@@ -105,9 +103,170 @@ pub async fn session_stats(
         .signed_duration_since(state.start_time)
         .num_seconds() as u64;
 
+    let sessions = state.sessions.all().await;
+    let mut states = std::collections::BTreeMap::new();
+    for session in &sessions {
+        *states.entry(session.state.as_str().to_string()).or_insert(0) += 1;
+    }
+
     Json(StatsResponse {
-        active_sessions: state.sessions.len(),
+        active_sessions: sessions.len(),
+        states,
         total_calls: state.total_calls.load(std::sync::atomic::Ordering::SeqCst),
         uptime_seconds: uptime,
     })
 }
+
+const DEFAULT_CALLS_LIMIT: u32 = 50;
+const MAX_CALLS_LIMIT: u32 = 500;
+
+/// GET /api/calls?estado=listening&direccion=inbound&telefono=300&limit=50&offset=0
+pub async fn list_calls(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<CallsQuery>,
+) -> Result<Json<CallList>, (StatusCode, Json<ErrorResponse>)> {
+    let estado = match query.estado.as_deref() {
+        Some(value) => Some(CallState::from_code(value).ok_or_else(|| invalid_filter("estado", value))?),
+        None => None,
+    };
+    let direccion = match query.direccion.as_deref() {
+        Some(value) => Some(CallDirection::from_code(value).ok_or_else(|| invalid_filter("direccion", value))?),
+        None => None,
+    };
+    let telefono = query.telefono.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    let limit = query.limit.unwrap_or(DEFAULT_CALLS_LIMIT).min(MAX_CALLS_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+
+    let mut sessions: Vec<_> = state.sessions.all().await
+        .into_iter()
+        .filter(|s| estado.is_none_or(|estado| s.state == estado))
+        .filter(|s| direccion.is_none_or(|direccion| s.direction == direccion))
+        .filter(|s| telefono.is_none_or(|telefono| s.telefono.contains(telefono)))
+        .collect();
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.created_at));
+
+    let now = chrono::Utc::now();
+    let calls = sessions
+        .iter()
+        .skip(offset as usize)
+        .take(limit as usize)
+        .map(|session| CallSummary::from_session(session, now))
+        .collect();
+
+    Ok(Json(CallList { total: sessions.len(), limit, offset, calls }))
+}
+
+/// GET /api/calls/{call_control_id}: sesión completa de una llamada en curso
+pub async fn call_detail(
+    State(state): State<Arc<AppState>>,
+    Path(call_control_id): Path<String>,
+) -> Result<Json<CallDetail>, (StatusCode, Json<ErrorResponse>)> {
+    let session = state.sessions.get(&call_control_id).await.ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Call not found".to_string(),
+                message: Some("Only active calls are listed; finished calls are in /api/cdr".to_string()),
+            }),
+        )
+    })?;
+
+    let now = chrono::Utc::now();
+    Ok(Json(CallDetail {
+        duration_secs: (now - session.created_at).num_seconds().max(0),
+        idle_secs: (now - session.last_activity).num_seconds().max(0),
+        session,
+    }))
+}
+
+fn invalid_filter(name: &str, value: &str) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse {
+            error: "Invalid filter".to_string(),
+            message: Some(format!("Unknown {} '{}'", name, value)),
+        }),
+    )
+}
+
+/// GET /api/cdr?desde=2025-03-01&hasta=2025-03-31&telefono=300&limit=100&offset=0
+pub async fn call_records(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<CdrQuery>,
+) -> Result<Json<Vec<CallDetailRecord>>, (StatusCode, Json<ErrorResponse>)> {
+    let invalid_dates = [query.desde.as_deref(), query.hasta.as_deref()]
+        .into_iter()
+        .flatten()
+        .any(|value| cdr::parse_bound(value, false).is_none());
+    if invalid_dates {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Invalid date filter".to_string(),
+                message: Some("Use RFC 3339 or YYYY-MM-DD".to_string()),
+            }),
+        ));
+    }
+
+    state.cdr.query(query).await.map(Json).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Failed to query call records".to_string(),
+                message: Some(e.to_string()),
+            }),
+        )
+    })
+}
+
+/// Transcripción de una llamada: de la sesión si sigue en curso, de SQLite si ya terminó
+pub async fn call_transcript(
+    State(state): State<Arc<AppState>>,
+    Path(call_control_id): Path<String>,
+    Query(query): Query<TranscriptQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let as_text = match query.format.as_deref() {
+        None | Some("json") => false,
+        Some("text") => true,
+        Some(other) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "Invalid format".to_string(),
+                    message: Some(format!("Unknown format '{}', use json or text", other)),
+                }),
+            ));
+        }
+    };
+
+    let transcript = match state.sessions.get(&call_control_id).await {
+        Some(session) => CallTranscript { call_control_id, active: true, lines: session.transcript },
+        None => {
+            let lines = state.cdr.transcript(&call_control_id).await.map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "Failed to load transcript".to_string(),
+                        message: Some(e.to_string()),
+                    }),
+                )
+            })?;
+            if lines.is_empty() {
+                return Err((
+                    StatusCode::NOT_FOUND,
+                    Json(ErrorResponse {
+                        error: "Transcript not found".to_string(),
+                        message: None,
+                    }),
+                ));
+            }
+            CallTranscript { call_control_id, active: false, lines }
+        }
+    };
+
+    if as_text {
+        Ok(([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], transcript.to_text()).into_response())
+    } else {
+        Ok(Json(transcript).into_response())
+    }
+}
@@ -0,0 +1,119 @@
+use axum::{
+    extract::{Path, State, Json},
+    http::StatusCode,
+};
+use std::sync::Arc;
+use tracing::info;
+use crate::{
+    handlers::call::{reject_if_draining, validate_voice},
+    models::{CampaignDetail, CampaignRequest, CampaignStatus, CampaignSummary, ErrorResponse},
+    services::{AppState, campaign},
+};
+
+type CampaignResult<T> = Result<(StatusCode, Json<T>), (StatusCode, Json<ErrorResponse>)>;
+
+/// POST /api/campaigns: crea la campaña y empieza a marcar en segundo plano
+pub async fn create_campaign(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CampaignRequest>,
+) -> CampaignResult<CampaignSummary> {
+    reject_if_draining(&state)?;
+    if payload.contactos.is_empty() {
+        return Err(error(StatusCode::BAD_REQUEST, "Empty campaign", Some("contactos must not be empty".to_string())));
+    }
+    payload.contactos.iter().try_for_each(validate_voice)?;
+    let (default_concurrent, default_cps) = campaign::default_limits();
+    let max_concurrent = payload.max_concurrentes.unwrap_or(default_concurrent);
+    let cps = payload.cps.unwrap_or(default_cps);
+    if max_concurrent == 0 || cps == 0 {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            "Invalid limits",
+            Some("max_concurrentes and cps must be greater than 0".to_string()),
+        ));
+    }
+    if max_concurrent > campaign::MAX_CONCURRENT_LIMIT {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            "Invalid limits",
+            Some(format!("max_concurrentes must be at most {}", campaign::MAX_CONCURRENT_LIMIT)),
+        ));
+    }
+
+    let campaign = campaign::start(&state, payload, max_concurrent, cps);
+    Ok((StatusCode::CREATED, Json(campaign.summary())))
+}
+
+/// GET /api/campaigns
+pub async fn list_campaigns(
+    State(state): State<Arc<AppState>>,
+) -> Json<Vec<CampaignSummary>> {
+    Json(state.campaigns.list())
+}
+
+/// GET /api/campaigns/{campaign_id}: incluye el estado de cada contacto
+pub async fn campaign_detail(
+    State(state): State<Arc<AppState>>,
+    Path(campaign_id): Path<String>,
+) -> CampaignResult<CampaignDetail> {
+    let campaign = state.campaigns.get(&campaign_id).ok_or_else(not_found)?;
+    Ok((StatusCode::OK, Json(campaign.detail())))
+}
+
+/// POST /api/campaigns/{campaign_id}/pause: no se marcan más contactos hasta reanudar
+pub async fn pause(
+    State(state): State<Arc<AppState>>,
+    Path(campaign_id): Path<String>,
+) -> CampaignResult<CampaignSummary> {
+    transition(&state, &campaign_id, &[CampaignStatus::Running], CampaignStatus::Paused)
+}
+
+/// POST /api/campaigns/{campaign_id}/resume
+pub async fn resume(
+    State(state): State<Arc<AppState>>,
+    Path(campaign_id): Path<String>,
+) -> CampaignResult<CampaignSummary> {
+    reject_if_draining(&state)?;
+    transition(&state, &campaign_id, &[CampaignStatus::Paused], CampaignStatus::Running)
+}
+
+/// POST /api/campaigns/{campaign_id}/cancel: las llamadas ya marcadas siguen su curso
+pub async fn cancel(
+    State(state): State<Arc<AppState>>,
+    Path(campaign_id): Path<String>,
+) -> CampaignResult<CampaignSummary> {
+    transition(&state, &campaign_id, &[CampaignStatus::Running, CampaignStatus::Paused], CampaignStatus::Cancelled)
+}
+
+fn transition(
+    state: &Arc<AppState>,
+    campaign_id: &str,
+    from: &[CampaignStatus],
+    to: CampaignStatus,
+) -> CampaignResult<CampaignSummary> {
+    let campaign = state.campaigns.get(campaign_id).ok_or_else(not_found)?;
+    if !campaign.transition(from, to) {
+        let current = campaign.summary().status;
+        return Err(error(
+            StatusCode::CONFLICT,
+            "Invalid campaign state",
+            Some(format!("Campaign is {}", current.as_str())),
+        ));
+    }
+    info!("🎛️ [Campaña:{}] Estado → {}", campaign_id, to.as_str());
+    Ok((StatusCode::OK, Json(campaign.summary())))
+}
+
+fn not_found() -> (StatusCode, Json<ErrorResponse>) {
+    error(StatusCode::NOT_FOUND, "Campaign not found", None)
+}
+
+fn error(status: StatusCode, error: &str, message: Option<String>) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        status,
+        Json(ErrorResponse {
+            error: error.to_string(),
+            message,
+        }),
+    )
+}
@@ -0,0 +1,132 @@
+use axum::{
+    extract::{Path, State, Json},
+    http::StatusCode,
+};
+use std::sync::Arc;
+use serde_json::json;
+use tracing::info;
+use crate::{
+    models::{CallEventKind, ErrorResponse, PhraseRequest, SayRequest, SessionInfo},
+    services::{AppState, call_queue::{self, CallAction}},
+};
+
+type ControlResult = Result<(StatusCode, Json<serde_json::Value>), (StatusCode, Json<ErrorResponse>)>;
+
+/// POST /api/calls/{call_control_id}/hangup: cuelga cuando termine lo que ya está en cola
+pub async fn hangup(
+    State(state): State<Arc<AppState>>,
+    Path(call_control_id): Path<String>,
+) -> ControlResult {
+    active_call(&state, &call_control_id).await?;
+    queue(&state, &call_control_id, CallAction::Hangup).await
+}
+
+/// POST /api/calls/{call_control_id}/say {"texto": "..."}
+pub async fn say(
+    State(state): State<Arc<AppState>>,
+    Path(call_control_id): Path<String>,
+    Json(payload): Json<SayRequest>,
+) -> ControlResult {
+    let text = payload.texto.trim().to_string();
+    if text.is_empty() {
+        return Err(bad_request("Empty text", None));
+    }
+    active_call(&state, &call_control_id).await?;
+    queue(&state, &call_control_id, CallAction::Say { text }).await
+}
+
+/// POST /api/calls/{call_control_id}/phrase {"frase": "still_there"}
+pub async fn phrase(
+    State(state): State<Arc<AppState>>,
+    Path(call_control_id): Path<String>,
+    Json(payload): Json<PhraseRequest>,
+) -> ControlResult {
+    let session = active_call(&state, &call_control_id).await?;
+    if !call_queue::is_catalog_phrase(&payload.frase, session.language.unwrap_or_default()) {
+        return Err(bad_request("Unknown phrase", Some(format!("'{}' is not in the phrase catalog", payload.frase))));
+    }
+    queue(&state, &call_control_id, CallAction::Phrase { key: payload.frase }).await
+}
+
+/// POST /api/calls/{call_control_id}/pause: se sigue transcribiendo, pero el bot no responde
+pub async fn pause(
+    State(state): State<Arc<AppState>>,
+    Path(call_control_id): Path<String>,
+) -> ControlResult {
+    set_paused(&state, &call_control_id, true).await
+}
+
+/// POST /api/calls/{call_control_id}/resume
+pub async fn resume(
+    State(state): State<Arc<AppState>>,
+    Path(call_control_id): Path<String>,
+) -> ControlResult {
+    set_paused(&state, &call_control_id, false).await
+}
+
+async fn set_paused(state: &Arc<AppState>, call_id: &str, paused: bool) -> ControlResult {
+    let updated = state.sessions
+        .update(call_id, |session| std::mem::replace(&mut session.ai_paused, paused))
+        .await;
+    let Some(was_paused) = updated else {
+        return Err(not_found());
+    };
+
+    if was_paused != paused {
+        state.events.publish(call_id, CallEventKind::AiPaused { paused });
+        if paused {
+            info!("⏸️ [CALL:{}][Operador] IA en pausa", call_id);
+            // La respuesta especulativa en curso ya no se va a usar
+            if let Some(turns) = state.turns.get(call_id) {
+                turns.cancel();
+            }
+        } else {
+            info!("▶️ [CALL:{}][Operador] IA reanudada", call_id);
+        }
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "call_control_id": call_id,
+            "ai_paused": paused
+        })),
+    ))
+}
+
+async fn queue(state: &Arc<AppState>, call_id: &str, action: CallAction) -> ControlResult {
+    if !call_queue::enqueue(state, call_id, action).await {
+        return Err(not_found());
+    }
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(json!({
+            "call_control_id": call_id,
+            "status": "queued"
+        })),
+    ))
+}
+
+async fn active_call(state: &Arc<AppState>, call_id: &str) -> Result<SessionInfo, (StatusCode, Json<ErrorResponse>)> {
+    state.sessions.get(call_id).await.ok_or_else(not_found)
+}
+
+fn not_found() -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse {
+            error: "Call not found".to_string(),
+            message: Some("Only active calls can be controlled".to_string()),
+        }),
+    )
+}
+
+fn bad_request(error: &str, message: Option<String>) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse {
+            error: error.to_string(),
+            message,
+        }),
+    )
+}
@@ -0,0 +1,50 @@
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::stream::{self, Stream, StreamExt};
+use std::{convert::Infallible, sync::Arc};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+use crate::{
+    models::{CallEvent, EventsQuery},
+    services::AppState,
+};
+
+/// GET /api/events?call_control_id=...: eventos de las llamadas en vivo (Server-Sent Events)
+pub async fn call_events(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<EventsQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let filter = query.call_control_id.filter(|id| !id.is_empty());
+    info!("📺 [Events] Nuevo suscriptor (llamada: {})", filter.as_deref().unwrap_or("todas"));
+
+    let events = stream::unfold((state.events.subscribe(), filter), |(mut rx, filter)| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    if filter.as_ref().is_some_and(|id| *id != event.call_control_id) {
+                        continue;
+                    }
+                    return Some((Ok(to_sse(&event)), (rx, filter)));
+                }
+                // Suscriptor lento: se avisa cuántos eventos perdió y se sigue desde el más viejo disponible
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("⚠️ [Events] Suscriptor atrasado, {} eventos descartados", skipped);
+                    let event = Event::default().event("lagged").data(skipped.to_string());
+                    return Some((Ok(event), (rx, filter)));
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    // Al terminar el drenaje se corta el stream para que el servidor pueda cerrar
+    let stopped = async move { state.lifecycle.stopped().await };
+    Sse::new(events.take_until(stopped)).keep_alive(KeepAlive::default())
+}
+
+fn to_sse(event: &CallEvent) -> Event {
+    let data = serde_json::to_string(event).unwrap_or_default();
+    Event::default().event(event.kind.name()).data(data)
+}
@@ -1,6 +1,7 @@
 use axum::{
     extract::{State, ws::{WebSocket, WebSocketUpgrade, Message}},
-    response::IntoResponse,
+    http::StatusCode,
+    response::{IntoResponse, Response},
 };
 use std::sync::Arc;
 use tracing::{info, error, warn, debug};
@@ -8,13 +9,43 @@ use futures_util::StreamExt;
 use base64::{engine::general_purpose::STANDARD, Engine};
 use chrono::Timelike;
 
-use crate::services::{AppState, SessionManager, DeepgramWebSocket};
+use crate::models::{CallState, ClientState, Language, LatencyStage, Speaker, TranscriptSource};
+use crate::services::{AppState, SessionManager, app_state, TelnyxService, TranscriptEvent, filler, language, call_queue::{self, CallAction}, call_capture::CallCapture, session_reaper::SessionEndReason, silence::SilenceMonitor, turn::{self, TurnManager, TurnOutcome}};
+
+/// Pista de un frame `media` de Telnyx
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MediaTrack {
+    Inbound,
+    Outbound,
+}
+
+impl MediaTrack {
+    /// Sin campo `track` (stream solo inbound) se asume el cliente
+    fn from_frame(media: &serde_json::Value) -> Self {
+        match media.get("track").and_then(|t| t.as_str()) {
+            Some("outbound") => MediaTrack::Outbound,
+            _ => MediaTrack::Inbound,
+        }
+    }
+
+    fn speaker(self) -> Speaker {
+        match self {
+            MediaTrack::Inbound => Speaker::Caller,
+            MediaTrack::Outbound => Speaker::Bot,
+        }
+    }
+}
 
 /// Handler para conexión WebSocket de Telnyx Media Streams
 pub async fn handle_media_stream(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
-) -> impl IntoResponse {
+) -> Response {
+    // En drenaje solo siguen los streams ya abiertos
+    if state.lifecycle.is_draining() {
+        warn!("🚫 [MediaStream] Stream rechazado: servicio en drenaje");
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
     ws.on_upgrade(move |socket| handle_socket(socket, state))
 }
 
@@ -23,10 +54,10 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
 
     let (_, mut ws_receiver) = socket.split();
     // Canal para coalescer audio y reducir overhead de frames pequeños
-    let (coalesce_tx, mut coalesce_rx) = tokio::sync::mpsc::channel::<Vec<u8>>(256);
+    let (coalesce_tx, coalesce_rx) = tokio::sync::mpsc::channel::<Vec<u8>>(256);
     
     // Esperamos el mensaje "start" que trae call_control_id; ignoramos handshakes como "connected".
-    let call_id = loop {
+    let (call_id, start) = loop {
         match ws_receiver.next().await {
             Some(Ok(Message::Text(text))) => {
                 match serde_json::from_str::<serde_json::Value>(&text) {
@@ -44,7 +75,7 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                                         .and_then(|c| c.as_str())
                                         .unwrap_or("n/a");
                                     info!("📞 [CALL:{}][MediaStream] START recibido (stream_id={})", call_id, stream_id);
-                                    break call_id;
+                                    break (call_id, json["start"].clone());
                                 }
                                 other => {
                                     warn!("⚠️ [MediaStream] Mensaje inicial ignorado (event={}): {}", other, text);
@@ -77,30 +108,68 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
         }
     };
 
-    // Crear sesión
-    let session = SessionManager::create_session(
-        call_id.clone(),
-        "Cliente".to_string(),
-        "desconocido".to_string(),
-    );
-    state.sessions.insert(call_id.clone(), session);
-
-    // Conectar a Deepgram WebSocket
-    let deepgram = DeepgramWebSocket::new();
-    let (audio_tx, mut transcript_rx) = match deepgram.connect(call_id.clone()).await {
-        Ok(channels) => channels,
+    // Unirse a la sesión que abrió el webhook; si el stream llega primero (u otra réplica
+    // no la tiene), crearla con el client_state y los parámetros del mensaje start
+    let client_state = start.get("client_state")
+        .and_then(|c| c.as_str())
+        .and_then(ClientState::decode)
+        .unwrap_or_else(|| ClientState::unknown(&call_id))
+        .with_custom_parameters(&start["custom_parameters"]);
+    let session = state.sessions
+        .open(SessionManager::from_client_state(call_id.clone(), &client_state))
+        .await;
+    let (voz, stt_overrides, grabacion) = (session.voz, session.stt, session.grabacion);
+    // El start del stream implica llamada contestada aunque el webhook aún no haya llegado
+    state.claim_call_state(&call_id, &[CallState::Dialing, CallState::Ringing], CallState::Answered).await;
+    // Hangup, reaper u otra réplica pueden cerrar la llamada mientras el stream sigue abierto
+    let shutdown = state.register_stream(&call_id);
+
+    // Conectar al proveedor STT (Deepgram, Telnyx o replay según STT_PROVIDER)
+    let (audio_tx, mut transcript_rx) = match state.stt_provider.connect(call_id.clone(), stt_overrides.as_ref()).await {
+        Ok(stream) => (stream.audio_tx, stream.events),
         Err(e) => {
-            error!("❌ [CALL:{}] Error conectando a Deepgram: {}", call_id, e);
+            error!("❌ [CALL:{}] Error conectando STT ({}): {}", call_id, state.stt_provider.name(), e);
             return;
         }
     };
 
+    // Con STREAM_TRACK=both_tracks la voz del bot va a un segundo stream STT, solo para transcripción
+    let mut outbound_tx = if TelnyxService::stream_track() == "both_tracks" {
+        if state.stt_provider.supports_outbound() {
+            match state.stt_provider.connect(call_id.clone(), stt_overrides.as_ref()).await {
+                Ok(stream) => {
+                    let (tx, rx) = tokio::sync::mpsc::channel::<Vec<u8>>(256);
+                    let (_, stt_audio) = tokio::sync::watch::channel(stream.audio_tx);
+                    tokio::spawn(coalesce_audio(call_id.clone(), rx, stt_audio));
+                    tokio::spawn(record_bot_transcripts(state.clone(), call_id.clone(), stream.events));
+                    Some(tx)
+                }
+                Err(e) => {
+                    warn!("⚠️ [CALL:{}] Sin transcripción de la pista outbound: {}", call_id, e);
+                    None
+                }
+            }
+        } else {
+            warn!("⚠️ [CALL:{}] STT {} no transcribe la pista outbound", call_id, state.stt_provider.name());
+            None
+        }
+    } else {
+        None
+    };
+
     // Reproducir saludo
     tokio::spawn({
         let call_id = call_id.clone();
         let state = state.clone();
+        let voz = voz.clone();
         async move {
             tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+
+            // Si el webhook call.answered ya saludó, no se repite
+            if !state.claim_call_state(&call_id, &[CallState::Answered], CallState::Greeting).await {
+                info!("⏭️ [CALL:{}][TTS] Saludo ya en curso desde el webhook", call_id);
+                return;
+            }
             
             let hour = chrono::Utc::now().hour();
             let greeting_key = match hour {
@@ -111,19 +180,40 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
 
             info!("🔊 [CALL:{}][TTS] Reproduciendo saludo: {}", call_id, greeting_key);
             
-            if let Some(url) = state.get_or_generate_greeting(greeting_key).await {
-                if let Err(e) = state.telnyx_service.play_audio(&call_id, &url).await {
-                    error!("❌ [CALL:{}] Error reproduciendo saludo: {}", call_id, e);
+            if let Some(url) = state.get_or_generate_greeting(greeting_key, voz.as_ref()).await {
+                match state.telnyx_service.play_audio(&call_id, &url).await {
+                    Ok(_) => {
+                        if let Some(text) = app_state::greeting_text(greeting_key) {
+                            state.record_transcript(&call_id, Speaker::Bot, TranscriptSource::Phrase, text, None).await;
+                        }
+                    }
+                    Err(e) => error!("❌ [CALL:{}] Error reproduciendo saludo: {}", call_id, e),
                 }
             }
         }
     });
 
+    // VAD local sobre la pista del cliente: aviso y cuelgue por silencio (SILENCE_TIMEOUT_MS)
+    let silence = SilenceMonitor::start(state.clone(), call_id.clone());
+
     // Task para procesar audio de Telnyx → buffer de coalescing
     let call_id_audio = call_id.clone();
+    let state_audio = state.clone();
+    let mut capture = CallCapture::start(&call_id, grabacion);
+    let mut shutdown_audio = shutdown.clone();
     tokio::spawn(async move {
         let mut frame_count: u64 = 0;
-        while let Some(msg) = ws_receiver.next().await {
+        loop {
+            let msg = tokio::select! {
+                msg = ws_receiver.next() => match msg {
+                    Some(msg) => msg,
+                    None => break,
+                },
+                _ = shutdown_audio.changed() => {
+                    info!("🔚 [CALL:{}][MediaStream] Llamada cerrada, soltando el stream", call_id_audio);
+                    break;
+                }
+            };
             match msg {
                 Ok(Message::Text(text)) => {
                     // Parsear mensaje de Telnyx Media Stream
@@ -131,11 +221,13 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                         if let Some(event) = json.get("event").and_then(|e| e.as_str()) {
                             match event {
                                 "media" => {
+                                    let media = &json["media"];
+                                    let track = MediaTrack::from_frame(media);
+                                    if track == MediaTrack::Outbound && outbound_tx.is_none() && capture.is_none() {
+                                        continue;
+                                    }
                                     // Audio payload en base64 (mulaw)
-                                    if let Some(payload) = json.get("media")
-                                        .and_then(|m| m.get("payload"))
-                                        .and_then(|p| p.as_str())
-                                    {
+                                    if let Some(payload) = media.get("payload").and_then(|p| p.as_str()) {
                                         if let Ok(audio_data) = STANDARD.decode(payload) {
                                             frame_count += 1;
                                             if frame_count <= 5 {
@@ -143,10 +235,31 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                                             } else {
                                                 debug!("🎤 [CALL:{}][Telnyx->Deepgram] frame#{} bytes={} b64_len={}", call_id_audio, frame_count, audio_data.len(), payload.len());
                                             }
-                                            // Enviar al buffer de coalescing
-                                            if let Err(e) = coalesce_tx.send(audio_data).await {
-                                                error!("❌ [CALL:{}] Error en buffer de coalescing: {}", call_id_audio, e);
-                                                break;
+                                            if let (MediaTrack::Inbound, Some(monitor)) = (track, &silence) {
+                                                if let Some(vad_event) = monitor.feed_ulaw(&audio_data) {
+                                                    debug!("🎚️ [CALL:{}][VAD] {:?}", call_id_audio, vad_event);
+                                                }
+                                            }
+                                            if let Some(capture) = capture.as_mut() {
+                                                capture.push(track.speaker(), &audio_data);
+                                            }
+                                            // Enviar al buffer de coalescing de su pista
+                                            match track {
+                                                MediaTrack::Inbound => {
+                                                    if let Err(e) = coalesce_tx.send(audio_data).await {
+                                                        error!("❌ [CALL:{}] Error en buffer de coalescing: {}", call_id_audio, e);
+                                                        break;
+                                                    }
+                                                }
+                                                // Perder la transcripción del bot no corta la llamada
+                                                MediaTrack::Outbound => {
+                                                    if let Some(tx) = outbound_tx.as_ref() {
+                                                        if let Err(e) = tx.send(audio_data).await {
+                                                            warn!("⚠️ [CALL:{}] Buffer de la pista outbound cerrado, se deja de transcribir al bot: {}", call_id_audio, e);
+                                                            outbound_tx = None;
+                                                        }
+                                                    }
+                                                }
                                             }
                                         }
                                     }
@@ -171,148 +284,144 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                 _ => {}
             }
         }
+
+        // Sin stream no hay llamada que atender: sesión, turnos, silencio y STT se cierran
+        state_audio.end_call(&call_id_audio, SessionEndReason::StreamStopped).await;
+
+        if let Some(capture) = capture {
+            capture.finish(&state_audio.s3_service).await;
+        }
     });
 
-    // Task de coalescing: agrupa frames y los envía periódicamente a Deepgram
-    let call_id_flush = call_id.clone();
+    // Task de coalescing: agrupa frames y los envía periódicamente al STT.
+    // El destino va en un watch para poder reconectar el STT si cambia el idioma.
+    let (stt_audio_swap, stt_audio) = tokio::sync::watch::channel(audio_tx);
+    tokio::spawn(coalesce_audio(call_id.clone(), coalesce_rx, stt_audio));
+
+    // Task para procesar transcripts STT → Claude → cola de la llamada
+    let call_id_transcript = call_id.clone();
+    let state_transcript = state.clone();
+    let coordinator = state.turn_coordinator(&call_id);
+    let mut shutdown_transcript = shutdown;
     tokio::spawn(async move {
-        let mut buffer: Vec<u8> = Vec::with_capacity(4096);
-        let mut last_flush = tokio::time::Instant::now();
-        let flush_interval = tokio::time::Duration::from_millis(40);
+        // Los finales con confianza baja no entran al turno (TRANSCRIPTION_MIN_CONFIDENCE)
+        let min_confidence = turn::min_confidence();
+        let mut turns = TurnManager::with_min_confidence(min_confidence);
+
+        // Al salir se suelta el emisor de audio del STT y su socket se cierra
         loop {
-            tokio::select! {
-                maybe_chunk = coalesce_rx.recv() => {
-                    match maybe_chunk {
-                        Some(chunk) => {
-                            buffer.extend_from_slice(&chunk);
-                            if buffer.len() >= 2048 {
-                                if let Err(e) = audio_tx.send(std::mem::take(&mut buffer)).await {
-                                    error!("❌ [CALL:{}] Error enviando audio coalesced: {}", call_id_flush, e);
-                                    break;
-                                }
-                                last_flush = tokio::time::Instant::now();
-                            }
-                        }
-                        None => {
-                            // canal cerrado
-                            if !buffer.is_empty() {
-                                let _ = audio_tx.send(std::mem::take(&mut buffer)).await;
-                            }
-                            info!("🔚 [CALL:{}] Coalescing finalizado", call_id_flush);
-                            break;
-                        }
-                    }
+            let event = tokio::select! {
+                event = transcript_rx.recv() => match event {
+                    Some(event) => event,
+                    None => break,
+                },
+                _ = shutdown_transcript.changed() => break,
+            };
+            if event.is_final && event.has_text() && event.confidence < min_confidence {
+                warn!("⚠️ [CALL:{}] Confianza baja: {} ({})", call_id_transcript, event.confidence, event.text);
+            }
+
+            // Adelantar la llamada a Claude con el texto provisional del turno;
+            // si el final coincide se reutiliza, si no se aborta
+            let preview = turns.preview(&event).filter(|_| !event.is_final || event.confidence >= min_confidence);
+            let turn = match turns.push(&event) {
+                Some(TurnOutcome::Completed(turn)) => turn,
+                Some(TurnOutcome::Unclear { text, confidence }) => {
+                    turn::handle_unclear_turn(&state_transcript, &call_id_transcript, &text, confidence).await;
+                    continue;
                 }
-                _ = tokio::time::sleep(flush_interval) => {
-                    if !buffer.is_empty() && last_flush.elapsed() >= flush_interval {
-                        if let Err(e) = audio_tx.send(std::mem::take(&mut buffer)).await {
-                            error!("❌ [CALL:{}] Error enviando audio coalesced (timer): {}", call_id_flush, e);
-                            break;
+                None => {
+                    if let Some(preview) = preview {
+                        let speculating = coordinator.speculate(&preview, || {
+                            turn::spawn_response(state_transcript.clone(), call_id_transcript.clone(), preview.clone())
+                        });
+                        if speculating {
+                            debug!("⚡ [CALL:{}][Claude] Respuesta especulativa para: '{}'", call_id_transcript, preview);
                         }
-                        last_flush = tokio::time::Instant::now();
                     }
+                    continue;
                 }
-            }
-        }
-    });
+            };
+            let text = &turn.text;
+            let wc = text.split_whitespace().count();
+            info!("💬 [CALL:{}][STT->App] TURNO (conf {:.2}, words {}): '{}'", call_id_transcript, turn.confidence, wc, text);
+            // Desde el fin del habla (VAD local) hasta el turno cerrado
+            let stt_latency_ms = state_transcript.silence_monitors
+                .get(&call_id_transcript)
+                .and_then(|monitor| monitor.take_speech_end_elapsed())
+                .map(|elapsed| elapsed.as_millis() as u64);
 
-    // Cola FIFO para reproducir respuestas TTS sin solaparse
-    let (tts_tx, mut tts_rx) = tokio::sync::mpsc::channel::<String>(100);
+            // Claude se llama una vez por turno real del llamante
+            let start = match coordinator.finalize(text) {
+                Some(start) => start,
+                None => {
+                    info!("⏭️ [CALL:{}] Turno duplicado ignorado: '{}'", call_id_transcript, text);
+                    continue;
+                }
+            };
+            let ticket = start.ticket();
 
-    // Worker de reproducción: toma respuestas de la cola y las reproduce en orden
-    let call_id_tts_worker = call_id.clone();
-    let state_tts_worker = state.clone();
-    tokio::spawn(async move {
-        while let Some(response_text) = tts_rx.recv().await {
-            // Generar audio con ElevenLabs
-            match state_tts_worker.elevenlabs_service.text_to_speech(&response_text).await {
-                Ok(audio_bytes) => {
-                    let audio_key = format!("audio/response_{}_{}.mp3", 
-                        call_id_tts_worker, 
-                        chrono::Utc::now().timestamp()
-                    );
-
-                    // Subir a S3
-                    match state_tts_worker.s3_service.upload_audio(&audio_key, audio_bytes).await {
-                        Ok(url) => {
-                            info!("🔊 [CALL:{}][TTS] Audio generado y subido: {}", call_id_tts_worker, url);
-                            // Reproducir audio
-                            if let Err(e) = state_tts_worker.telnyx_service.play_audio(&call_id_tts_worker, &url).await {
-                                error!("❌ [CALL:{}] Error reproduciendo audio: {}", call_id_tts_worker, e);
-                            }
-                        }
-                        Err(e) => {
-                            error!("❌ [CALL:{}] Error subiendo audio a S3: {}", call_id_tts_worker, e);
-                        }
+            // El primer turno fija el idioma; si cambia, la especulación (en el idioma anterior) no sirve
+            let start = match language::settle(&state_transcript, &call_id_transcript, turn.language.as_deref(), text).await {
+                Some(new_language) => {
+                    if let Some(events) = restart_stt_for_language(&state_transcript, &call_id_transcript, new_language, &stt_audio_swap).await {
+                        transcript_rx = events;
+                        turns = TurnManager::with_min_confidence(min_confidence);
                     }
+                    start.without_speculation()
                 }
-                Err(e) => {
-                    error!("❌ [CALL:{}] Error generando audio: {}", call_id_tts_worker, e);
-                }
-            }
-        }
-        info!("🔚 [CALL:{}] TTS worker finalizado", call_id_tts_worker);
-    });
+                None => start,
+            };
 
-    // Task para procesar transcripts de Deepgram → Claude → push a cola TTS
-    let call_id_transcript = call_id.clone();
-    let state_transcript = state.clone();
-    tokio::spawn(async move {
-        while let Some(transcript) = transcript_rx.recv().await {
-            if transcript.channel.alternatives.is_empty() {
+            state_transcript
+                .record_transcript(&call_id_transcript, Speaker::Caller, TranscriptSource::SttFinal, text, Some(turn.confidence))
+                .await;
+            let updated = state_transcript.sessions.update(&call_id_transcript, |session| {
+                if session.ai_paused {
+                    return None;
+                }
+                SessionManager::clear_low_confidence_streak(session);
+                SessionManager::record_turn(session);
+                Some((session.voz.clone(), session.language.unwrap_or_default()))
+            }).await;
+            let Some(reply_to) = updated else {
+                continue;
+            };
+            let Some((voz, call_language)) = reply_to else {
+                info!("⏸️ [CALL:{}] IA en pausa: turno registrado sin respuesta", call_id_transcript);
                 continue;
+            };
+            if let Some(ms) = stt_latency_ms {
+                state_transcript.record_latency(&call_id_transcript, LatencyStage::Stt, ms).await;
             }
 
-            let text = &transcript.channel.alternatives[0].transcript;
-            let confidence = transcript.channel.alternatives[0].confidence;
+            state_transcript.set_call_state(&call_id_transcript, CallState::Thinking).await;
 
-            // Filtrar por confianza mínima
-            if confidence < 0.6 {
-                warn!("⚠️ [CALL:{}] Confianza baja: {} ({})", call_id_transcript, confidence, text);
-                continue;
-            }
+            // El relleno se cancela en la cola de la llamada cuando el audio real está listo
+            let pending_filler = filler::arm(state_transcript.clone(), call_id_transcript.clone(), voz, call_language);
 
-            // Procesar solo si tiene suficiente contenido
-            let word_count = text.split_whitespace().count();
-            let ends_sentence = text.trim_end().ends_with('.') || text.trim_end().ends_with('?') || text.trim_end().ends_with('!');
-            let should_process = transcript.is_final || (word_count >= 4 && (text.len() >= 14 || ends_sentence));
+            let response = start
+                .into_response(turn::generate_response(state_transcript.clone(), call_id_transcript.clone(), text.clone()))
+                .await;
 
-            if !should_process {
-                continue;
-            }
+            match response {
+                Ok(response) => {
+                    info!("🤖 [CALL:{}][Claude] Respuesta: '{}'", call_id_transcript, response);
 
-            let marker = if transcript.is_final {"FINAL"} else {"INTERIM"};
-            let wc = word_count;
-            let conf = confidence;
-            info!("💬 [CALL:{}][Deepgram->App] {} (conf {:.2}, words {}): '{}'", call_id_transcript, marker, conf, wc, text);
-
-            // Obtener sesión y contexto
-            if let Some(mut session_ref) = state_transcript.sessions.get_mut(&call_id_transcript) {
-                let context = SessionManager::get_conversation_context(&session_ref);
-
-                // Generar respuesta con Claude
-                match state_transcript.claude_service
-                    .generate_response(
-                        text,
-                        &session_ref.nombre,
-                        if context.is_empty() { None } else { Some(&context) },
-                    )
-                    .await
-                {
-                    Ok(response) => {
-                        info!("🤖 [CALL:{}][Claude] Respuesta: '{}'", call_id_transcript, response);
-
-                        // Agregar a historial
-                        SessionManager::add_to_history(&mut session_ref, response.clone());
-                        // Empujar respuesta a la cola TTS para reproducción ordenada
-                        if let Err(e) = tts_tx.send(response).await {
-                            error!("❌ [CALL:{}] Error encolar respuesta TTS: {}", call_id_transcript, e);
-                        }
-                    }
-                    Err(e) => {
-                        error!("❌ [CALL:{}] Error generando respuesta Claude: {}", call_id_transcript, e);
+                    // Agregar a historial
+                    state_transcript.sessions
+                        .update(&call_id_transcript, |session| SessionManager::add_to_history(session, response.clone()))
+                        .await;
+                    // Empujar respuesta a la cola de la llamada para reproducción ordenada
+                    let reply = CallAction::Reply { text: response, filler: pending_filler, ticket };
+                    if !call_queue::enqueue(&state_transcript, &call_id_transcript, reply).await {
+                        error!("❌ [CALL:{}] Error encolar respuesta TTS: cola cerrada", call_id_transcript);
                     }
                 }
+                Err(e) => {
+                    error!("❌ [CALL:{}] Error generando respuesta Claude: {}", call_id_transcript, e);
+                    state_transcript.claim_call_state(&call_id_transcript, &[CallState::Thinking], CallState::Listening).await;
+                }
             }
         }
 
@@ -321,3 +430,103 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
 
     info!("✅ [CALL:{}] Pipeline WebSocket completo configurado", call_id);
 }
+
+/// Agrupa frames pequeños y los envía periódicamente al stream STT
+async fn coalesce_audio(
+    call_id: String,
+    mut coalesce_rx: tokio::sync::mpsc::Receiver<Vec<u8>>,
+    stt_audio: tokio::sync::watch::Receiver<tokio::sync::mpsc::Sender<Vec<u8>>>,
+) {
+    let mut buffer: Vec<u8> = Vec::with_capacity(4096);
+    let mut last_flush = tokio::time::Instant::now();
+    let flush_interval = tokio::time::Duration::from_millis(40);
+    loop {
+        tokio::select! {
+            maybe_chunk = coalesce_rx.recv() => {
+                match maybe_chunk {
+                    Some(chunk) => {
+                        buffer.extend_from_slice(&chunk);
+                        if buffer.len() >= 2048 {
+                            let audio_tx = stt_audio.borrow().clone();
+                            if let Err(e) = audio_tx.send(std::mem::take(&mut buffer)).await {
+                                error!("❌ [CALL:{}] Error enviando audio coalesced: {}", call_id, e);
+                                break;
+                            }
+                            last_flush = tokio::time::Instant::now();
+                        }
+                    }
+                    None => {
+                        // canal cerrado
+                        if !buffer.is_empty() {
+                            let audio_tx = stt_audio.borrow().clone();
+                            let _ = audio_tx.send(std::mem::take(&mut buffer)).await;
+                        }
+                        info!("🔚 [CALL:{}] Coalescing finalizado", call_id);
+                        break;
+                    }
+                }
+            }
+            _ = tokio::time::sleep(flush_interval) => {
+                if !buffer.is_empty() && last_flush.elapsed() >= flush_interval {
+                    let audio_tx = stt_audio.borrow().clone();
+                    if let Err(e) = audio_tx.send(std::mem::take(&mut buffer)).await {
+                        error!("❌ [CALL:{}] Error enviando audio coalesced (timer): {}", call_id, e);
+                        break;
+                    }
+                    last_flush = tokio::time::Instant::now();
+                }
+            }
+        }
+    }
+}
+
+/// Finales de la pista outbound: lo que el cliente oyó del bot. Solo se registran,
+/// nunca disparan al LLM.
+async fn record_bot_transcripts(
+    state: Arc<AppState>,
+    call_id: String,
+    mut events: tokio::sync::mpsc::Receiver<TranscriptEvent>,
+) {
+    while let Some(event) = events.recv().await {
+        if !event.is_final || !event.has_text() {
+            continue;
+        }
+        info!("🗣️ [CALL:{}][STT][bot] '{}' (conf {:.2})", call_id, event.text, event.confidence);
+        state.record_transcript(&call_id, Speaker::Bot, TranscriptSource::SttFinal, &event.text, Some(event.confidence)).await;
+    }
+}
+
+/// Reconecta el STT de la pista del cliente en el idioma detectado, si el proveedor
+/// transcribe con un idioma fijo distinto. Devuelve los eventos del nuevo stream.
+async fn restart_stt_for_language(
+    state: &Arc<AppState>,
+    call_id: &str,
+    new_language: Language,
+    stt_audio: &tokio::sync::watch::Sender<tokio::sync::mpsc::Sender<Vec<u8>>>,
+) -> Option<tokio::sync::mpsc::Receiver<TranscriptEvent>> {
+    // `settle` ya dejó el idioma nuevo en los overrides de la sesión
+    let overrides = state.sessions.get(call_id).await.and_then(|s| s.stt.clone());
+    // Proveedores sin idioma fijo (o Deepgram en modo "multi") siguen tal cual
+    if state.stt_provider.language(overrides.as_ref())? == "multi" {
+        return None;
+    }
+    // Telnyx transcribe del lado del servidor: se detiene antes de reiniciar en el idioma nuevo
+    if state.stt_provider.name() == "telnyx" {
+        if let Err(e) = state.telnyx_service.stop_transcription(call_id).await {
+            error!("❌ [CALL:{}] Error deteniendo transcripción para cambiar idioma: {}", call_id, e);
+        }
+    }
+
+    match state.stt_provider.connect(call_id.to_string(), overrides.as_ref()).await {
+        Ok(stream) => {
+            info!("🌐 [CALL:{}] STT reconectado en {}", call_id, new_language.code());
+            // Al reemplazar el sender se cierra el stream anterior
+            let _ = stt_audio.send(stream.audio_tx);
+            Some(stream.events)
+        }
+        Err(e) => {
+            error!("❌ [CALL:{}] Error reconectando STT en {}: {}", call_id, new_language.code(), e);
+            None
+        }
+    }
+}
@@ -2,3 +2,6 @@ pub mod call;
 pub mod webhook;
 pub mod test;
 pub mod media_stream;
+pub mod control;
+pub mod events;
+pub mod campaign;
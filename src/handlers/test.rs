@@ -6,6 +6,7 @@ use axum::{
 use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 use tracing::info;
+use crate::models::Language;
 use crate::services::AppState;
 
 #[derive(Debug, Deserialize)]
@@ -39,18 +40,19 @@ pub async fn test_claude(
         &payload.mensaje,
         &payload.nombre,
         payload.contexto.as_deref(),
+        Language::Es,
     ).await {
-        Ok(response) => {
-            info!("✅ [TEST CLAUDE] Prueba exitosa. Respuesta: '{}'", response);
+        Ok(reply) => {
+            info!("✅ [TEST CLAUDE] Prueba exitosa. Respuesta: '{}'", reply.text);
             (
                 StatusCode::OK,
                 Json(TestClaudeResponse {
                     success: true,
                     model: std::env::var("CLAUDE_MODEL")
                         .unwrap_or_else(|_| "claude-3-5-haiku-20241022".to_string()),
-                    input_tokens: None, // Claude service no expone estos datos actualmente
-                    output_tokens: None,
-                    response: Some(response),
+                    input_tokens: Some(reply.input_tokens),
+                    output_tokens: Some(reply.output_tokens),
+                    response: Some(reply.text),
                     error: None,
                 })
             )
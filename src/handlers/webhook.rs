@@ -4,11 +4,10 @@ use axum::{
 };
 use std::sync::Arc;
 use tracing::{info, error, debug};
-use base64::{engine::general_purpose::STANDARD, Engine};
 use serde_json::json;
 use crate::{
-    models::ClientState, // Limpié los imports no usados para que no salgan warnings
-    services::{AppState, SessionManager},
+    models::{CallDirection, CallEventKind, CallState, ClientState, Speaker, TranscriptSource, UNKNOWN_CALLER_PHONE}, // Limpié los imports no usados para que no salgan warnings
+    services::{AppState, SessionManager, TranscriptEvent, app_state, call_queue::{self, CallAction}, filler, language, turn, session_reaper::SessionEndReason},
 };
 use chrono::{Timelike, FixedOffset, Utc}; // ✅ Necesario para .hour() y zona horaria Bogotá
 
@@ -35,6 +34,7 @@ pub async fn handle_telnyx_webhook(
     info!("📨 [CALL:{}] Webhook recibido: {}", call_id, event_type);
 
     match event_type {
+        "call.initiated" => handle_call_initiated(state, payload).await,
         "call.answered" => handle_call_answered(state, payload).await,
         "call.speak.ended" => handle_speak_ended(state, payload).await,
         "call.playback.started" => handle_playback_started(state, payload).await,
@@ -43,6 +43,7 @@ pub async fn handle_telnyx_webhook(
         "call.transcription.transcript_received" => handle_transcription(state, payload).await,
         "call.transcription.transcribed" => handle_transcription(state, payload).await,
         "call.transcription.partial" => handle_transcription_partial(state, payload).await,
+        "call.dtmf.received" => handle_dtmf(state, payload).await,
         "call.hangup" => handle_hangup(state, payload).await,
         _ => {
             // Log completo del payload para diagnóstico (cuando no encontramos `meta.event_type`)
@@ -52,6 +53,41 @@ pub async fn handle_telnyx_webhook(
     }
 }
 
+/// Las salientes ya tienen sesión (Dialing) desde `/api/call/initiate`; las entrantes
+/// la abren aquí mientras timbran
+async fn handle_call_initiated(
+    state: Arc<AppState>,
+    payload: serde_json::Value,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let data = if payload["data"]["payload"].is_object() { &payload["data"]["payload"] } else { &payload["data"] };
+    let call_control_id = match data["call_control_id"].as_str() {
+        Some(id) => id.to_string(),
+        None => return (StatusCode::BAD_REQUEST, Json(json!({"error": "Missing call_control_id"}))),
+    };
+
+    if data["direction"].as_str() != Some("incoming") {
+        return (StatusCode::OK, Json(json!({"status": "handled"})));
+    }
+
+    let mut client_state = data["client_state"].as_str()
+        .and_then(ClientState::decode)
+        .unwrap_or_else(|| ClientState::unknown(&call_control_id));
+    if let Some(from) = data["from"].as_str().filter(|_| client_state.telefono == UNKNOWN_CALLER_PHONE) {
+        client_state.telefono = from.to_string();
+    }
+    let mut session = SessionManager::from_client_state(call_control_id.clone(), &client_state);
+    session.direction = CallDirection::Inbound;
+    let session = state.sessions.open(session).await;
+    state.events.publish(&call_control_id, CallEventKind::Started {
+        direction: session.direction,
+        nombre: session.nombre,
+        telefono: session.telefono,
+    });
+    state.claim_call_state(&call_control_id, &[CallState::Dialing], CallState::Ringing).await;
+
+    (StatusCode::OK, Json(json!({"status": "handled"})))
+}
+
 async fn handle_call_answered(
     state: Arc<AppState>,
     payload: serde_json::Value,
@@ -69,32 +105,17 @@ async fn handle_call_answered(
     let client_state_base64 = payload["data"]["client_state"].as_str()
         .or_else(|| payload["data"]["payload"]["client_state"].as_str());
     
-    let mut client_state = ClientState {
-        nombre: "Cliente".to_string(),
-        telefono: "desconocido".to_string(),
-        contexto: None,
-        call_control_id: Some(call_control_id.clone()),
-    };
-
-    if let Some(b64) = client_state_base64 {
-        if let Ok(decoded) = STANDARD.decode(b64) {
-            if let Ok(decoded_str) = String::from_utf8(decoded) {
-                if let Ok(parsed) = serde_json::from_str::<ClientState>(&decoded_str) {
-                    client_state = parsed;
-                    client_state.call_control_id = Some(call_control_id.clone());
-                }
-            }
-        }
-    }
-
-    // Crear sesión
-    let session = SessionManager::create_session(
-        call_control_id.clone(),
-        client_state.nombre.clone(),
-        client_state.telefono.clone(),
-    );
-
-    state.sessions.insert(call_control_id.clone(), session);
+    let mut client_state = client_state_base64
+        .and_then(ClientState::decode)
+        .unwrap_or_else(|| ClientState::unknown(&call_control_id));
+    client_state.call_control_id = Some(call_control_id.clone());
+
+    // Crear sesión (o completar la que ya abrió el media stream)
+    let session = state.sessions
+        .open(SessionManager::from_client_state(call_control_id.clone(), &client_state))
+        .await;
+    state.claim_call_state(&call_control_id, &[CallState::Dialing, CallState::Ringing], CallState::Answered).await;
+    state.campaigns.call_answered(&call_control_id);
 
     // Generar saludo usando hora de Bogotá (UTC-5)
     let bogota_tz = FixedOffset::west_opt(5 * 3600).unwrap();
@@ -105,15 +126,25 @@ async fn handle_call_answered(
         _ => "evening",
     };
 
-    info!("🔊 Obteniendo saludo para: {}. ID: {}", greeting_key, call_control_id);
+    // Webhook y media stream compiten por saludar: solo quien pasa la llamada a Greeting lo hace
+    if state.claim_call_state(&call_control_id, &[CallState::Answered], CallState::Greeting).await {
+        info!("🔊 Obteniendo saludo para: {}. ID: {}", greeting_key, call_control_id);
 
-    // Obtener o generar audio bajo demanda y reproducir saludo
-    if let Some(url) = state.get_or_generate_greeting(greeting_key).await {
-        if let Err(e) = state.telnyx_service.play_audio(&call_control_id, &url).await {
-            error!("❌ Error reproduciendo audio: {}", e);
+        // Obtener o generar audio bajo demanda y reproducir saludo
+        if let Some(url) = state.get_or_generate_greeting(greeting_key, session.voz.as_ref()).await {
+            match state.telnyx_service.play_audio(&call_control_id, &url).await {
+                Ok(_) => {
+                    if let Some(text) = app_state::greeting_text(greeting_key) {
+                        state.record_transcript(&call_control_id, Speaker::Bot, TranscriptSource::Phrase, text, None).await;
+                    }
+                }
+                Err(e) => error!("❌ Error reproduciendo audio: {}", e),
+            }
+        } else {
+            error!("⚠️ No se pudo obtener saludo para: {}", greeting_key);
         }
     } else {
-        error!("⚠️ No se pudo obtener saludo para: {}", greeting_key);
+        info!("⏭️ [CALL:{}] Saludo ya en curso desde el media stream", call_control_id);
     }
 
     let use_media_streams = std::env::var("USE_MEDIA_STREAMS")
@@ -144,11 +175,11 @@ async fn handle_call_answered(
         tokio::spawn(async move {
             tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
             
-            if let Some(mut sess) = session_mgr.get_mut(&call_id_for_transcription) {
-                sess.transcription_started = true;
-            }
+            session_mgr
+                .update(&call_id_for_transcription, |sess| sess.transcription_started = true)
+                .await;
             
-            if let Err(e) = telnyx_svc.start_transcription(&call_id_for_transcription).await {
+            if let Err(e) = telnyx_svc.start_transcription(&call_id_for_transcription, None).await {
                 error!("❌ [CALL:{}] Error iniciando transcripción paralela: {}", call_id_for_transcription, e);
             } else {
                 info!("✅ [CALL:{}] Transcripción iniciada EN PARALELO con saludo", call_id_for_transcription);
@@ -160,15 +191,15 @@ async fn handle_call_answered(
 
     // ✅ Log corregido
     info!("✅ Llamada contestada y saludo enviado. Nombre: {}, Tel: {}", 
-        client_state.nombre,
-        client_state.telefono
+        session.nombre,
+        session.telefono
     );
 
     (StatusCode::OK, Json(json!({"status": "handled"})))
 }
 
 async fn handle_speak_ended(
-    _state: Arc<AppState>,
+    state: Arc<AppState>,
     payload: serde_json::Value,
 ) -> (StatusCode, Json<serde_json::Value>) {
     let call_control_id = match payload["data"]["call_control_id"].as_str()
@@ -178,14 +209,17 @@ async fn handle_speak_ended(
         None => return (StatusCode::BAD_REQUEST, Json(json!({"error": "Missing call_control_id"}))),
     };
 
-    // 📝 Transcripción ya se inicia en call.answer, aquí solo registramos el evento
+    // 📝 Transcripción ya se inicia en call.answer; speak solo lo usa el operador sin ElevenLabs
     info!("🎤 [CALL:{}] Evento speak_ended recibido", call_control_id);
+    call_queue::playback_ended(&state, &call_control_id, &payload);
+    state.touch_silence_monitor(&call_control_id);
+    state.claim_call_state(&call_control_id, &[CallState::Speaking], CallState::Listening).await;
 
     (StatusCode::OK, Json(json!({"status": "handled"})))
 }
 
 async fn handle_playback_started(
-    _state: Arc<AppState>,
+    state: Arc<AppState>,
     payload: serde_json::Value,
 ) -> (StatusCode, Json<serde_json::Value>) {
     let call_control_id = match payload["data"]["call_control_id"].as_str()
@@ -197,6 +231,7 @@ async fn handle_playback_started(
 
     // 📝 Ya iniciamos transcripción en handle_call_answered, así que solo registramos que playback comenzó
     info!("▶️ [CALL:{}] Playback iniciado", call_control_id);
+    state.touch_silence_monitor(&call_control_id);
 
     (StatusCode::OK, Json(json!({"status": "handled"})))
 }
@@ -212,6 +247,11 @@ async fn handle_playback_ended(
         None => return (StatusCode::BAD_REQUEST, Json(json!({"error": "Missing call_control_id"}))),
     };
 
+    call_queue::playback_ended(&state, &call_control_id, &payload);
+    // El silencio del cliente se cuenta desde que el bot termina de hablar
+    state.touch_silence_monitor(&call_control_id);
+    state.claim_call_state(&call_control_id, &[CallState::Greeting, CallState::Speaking], CallState::Listening).await;
+
     // ✅ Iniciar transcripción SOLO en modo webhook (cuando NO usamos Media Streams)
     let use_media_streams = std::env::var("USE_MEDIA_STREAMS")
         .unwrap_or_else(|_| "true".to_string())
@@ -220,13 +260,15 @@ async fn handle_playback_ended(
 
     if use_media_streams {
         info!("⏸️ [CALL:{}] Playback finalizado - Media Streams activo, sin iniciar transcripción Telnyx", call_control_id);
-    } else if let Some(mut session) = state.sessions.get_mut(&call_control_id) {
+    } else if let Some(session) = state.sessions.get(&call_control_id).await {
         if !session.transcription_started {
             info!("🎙️ [CALL:{}] Iniciando transcripción después del saludo", call_control_id);
-            if let Err(e) = state.telnyx_service.start_transcription(&call_control_id).await {
+            if let Err(e) = state.telnyx_service.start_transcription(&call_control_id, None).await {
                 error!("❌ Error iniciando transcripción: {}", e);
             } else {
-                session.transcription_started = true;
+                state.sessions
+                    .update(&call_control_id, |session| session.transcription_started = true)
+                    .await;
                 info!("✅ [CALL:{}] Transcripción iniciada - esperando audio del usuario", call_control_id);
             }
         } else {
@@ -258,80 +300,139 @@ async fn handle_transcription(
         .or_else(|| payload["data"]["payload"]["is_final"].as_bool())
         .or_else(|| payload["data"]["payload"]["transcription_data"]["is_final"].as_bool())
         .unwrap_or(false);
+    let confidence = payload["data"]["payload"]["transcription_data"]["confidence"].as_f64()
+        .or_else(|| payload["data"]["payload"]["confidence"].as_f64())
+        .unwrap_or(1.0);
 
     info!("📝 [CALL:{}] Evento transcripción - final: {}, texto: '{}'", call_control_id, is_final, transcript);
 
+    // En modo media stream con STT_PROVIDER=telnyx, el pipeline del stream consume el evento.
+    // Telnyx ya entrega finales por enunciado, así que cada final cierra el turno.
+    let event = if is_final {
+        TranscriptEvent::final_utterance(transcript, confidence)
+    } else {
+        TranscriptEvent::transcript(transcript, confidence, false)
+    };
+    if state.telnyx_transcripts.push(&call_control_id, event).await {
+        return (StatusCode::OK, Json(json!({"status": "forwarded"})));
+    }
+
     // Limpieza temprana
     let transcript_clean = sanitize_plain(transcript).trim().to_string();
-    
-    // 🚀 OPTIMIZACIÓN: Procesar transcripts intermedios si tienen contenido suficiente
-    // Esto reduce latencia de 6-12s a 1-3s
-    let word_count = transcript_clean.split_whitespace().count();
-    let should_process = is_final || (word_count >= 5 && transcript_clean.len() >= 15);
-
-    if transcript_clean.is_empty() || (!should_process && !is_final) {
-        info!("⏳ [CALL:{}] Ignorando (vacío o muy corto: {} palabras)", call_control_id, word_count);
+    if transcript_clean.is_empty() {
+        info!("⏳ [CALL:{}] Ignorando transcript vacío", call_control_id);
         return (StatusCode::OK, Json(json!({"status": "buffering"})));
     }
 
+    let turns = state.turn_coordinator(&call_control_id);
+    let min_confidence = turn::min_confidence();
+
+    // 🚀 OPTIMIZACIÓN: con un intermedio suficientemente largo se adelanta la llamada a Claude.
+    // Cuando llega el final se reutiliza esa respuesta (o se aborta si el texto cambió).
     if !is_final {
-        info!("⚡ [CALL:{}] Procesando transcript INTERMEDIO ({} palabras) - optimización de latencia", call_control_id, word_count);
+        if confidence < min_confidence {
+            return (StatusCode::OK, Json(json!({"status": "buffering"})));
+        }
+        let speculating = turns.speculate(&transcript_clean, || {
+            turn::spawn_response(state.clone(), call_control_id.clone(), transcript_clean.clone())
+        });
+        if speculating {
+            info!("⚡ [CALL:{}] Respuesta especulativa para transcript INTERMEDIO: '{}'", call_control_id, transcript_clean);
+            return (StatusCode::OK, Json(json!({"status": "speculating"})));
+        }
+        return (StatusCode::OK, Json(json!({"status": "buffering"})));
     }
 
     // Log de transcripción cruda y limpia
     info!("📝 [CALL:{}] Transcripción recibida: '{}'", call_control_id, transcript);
     info!("🧹 [CALL:{}] Transcripción limpia: '{}'", call_control_id, transcript_clean);
 
-    // Obtener sesión y generar respuesta
-    if let Some(mut session_ref) = state.sessions.get_mut(&call_control_id) {
-        let context = SessionManager::get_conversation_context(&session_ref);
+    // Telnyx entrega un final por enunciado: por debajo del umbral el turno es ininteligible
+    if confidence < min_confidence {
+        turn::handle_unclear_turn(&state, &call_control_id, &transcript_clean, confidence).await;
+        return (StatusCode::OK, Json(json!({"status": "low_confidence"})));
+    }
 
-        // Respuesta rápida opcional mientras se procesa la final
-        if is_quick_reply_enabled() {
-            if let Some(url) = state.get_or_generate_quick_reply("processing").await {
-                if let Err(e) = state.telnyx_service.play_audio(&call_control_id, &url).await {
-                    error!("❌ [CALL:{}] Error reproduciendo quick-reply: {}", call_control_id, e);
+    // Como mucho una respuesta por turno del llamante
+    let start = match turns.finalize(&transcript_clean) {
+        Some(start) => start,
+        None => {
+            info!("⏭️ [CALL:{}] Final duplicado ignorado", call_control_id);
+            return (StatusCode::OK, Json(json!({"status": "duplicate"})));
+        }
+    };
+    let ticket = start.ticket();
+
+    // El primer turno fija el idioma; Telnyx transcribe con idioma fijo, así que se reinicia
+    let start = match language::settle(&state, &call_control_id, None, &transcript_clean).await {
+        Some(new_language) => {
+            let telnyx = state.telnyx_service.clone();
+            let call_id = call_control_id.clone();
+            tokio::spawn(async move {
+                if let Err(e) = telnyx.stop_transcription(&call_id).await {
+                    error!("❌ [CALL:{}] Error deteniendo transcripción para cambiar idioma: {}", call_id, e);
                 }
-            }
+                if let Err(e) = telnyx.start_transcription(&call_id, Some(new_language)).await {
+                    error!("❌ [CALL:{}] Error reiniciando transcripción en {}: {}", call_id, new_language.code(), e);
+                }
+            });
+            start.without_speculation()
+        }
+        None => start,
+    };
+
+    state.record_transcript(&call_control_id, Speaker::Caller, TranscriptSource::SttFinal, &transcript_clean, Some(confidence)).await;
+    let updated = state.sessions.update(&call_control_id, |session| {
+        if session.ai_paused {
+            return None;
+        }
+        SessionManager::clear_low_confidence_streak(session);
+        SessionManager::record_turn(session);
+        Some((session.voz.clone(), session.language.unwrap_or_default()))
+    }).await;
+    let (voz, call_language) = match updated {
+        Some(Some(found)) => found,
+        Some(None) => {
+            info!("⏸️ [CALL:{}] IA en pausa: turno registrado sin respuesta", call_control_id);
+            return (StatusCode::OK, Json(json!({"status": "paused"})));
         }
+        None => {
+            error!("⚠️ [CALL:{}] Sesión no encontrada", call_control_id);
+            return (StatusCode::OK, Json(json!({"status": "handled"})));
+        }
+    };
+
+    state.set_call_state(&call_control_id, CallState::Thinking).await;
+
+    // Relleno solo si LLM + TTS no producen audio antes del umbral
+    let pending_filler = filler::arm(state.clone(), call_control_id.clone(), voz, call_language);
 
-        if let Ok(response) = state.claude_service
-            .generate_response(
-                &transcript_clean,
-                &session_ref.nombre,
-                if context.is_empty() { None } else { Some(&context) },
-            )
-            .await
-        {
+    let response = start
+        .into_response(turn::generate_response(state.clone(), call_control_id.clone(), transcript_clean.clone()))
+        .await;
+
+    match response {
+        Ok(response) => {
             let response_clean = sanitize_plain(&response);
 
-            SessionManager::add_to_history(&mut session_ref, response_clean.clone());
+            state.sessions
+                .update(&call_control_id, |session| SessionManager::add_to_history(session, response_clean.clone()))
+                .await;
 
             // Log de respuesta limpia antes de TTS
             info!("💬 [CALL:{}] Respuesta limpia: '{}'", call_control_id, response_clean);
 
-            // Generar audio con ElevenLabs, subir a S3 y reproducir
-            match state.elevenlabs_service.text_to_speech(&response_clean).await {
-                Ok(audio_bytes) => {
-                    let audio_key = format!("audio/response_{}_{}.mp3", 
-                        call_control_id, 
-                        chrono::Utc::now().timestamp()
-                    );
-                    match state.s3_service.upload_audio(&audio_key, audio_bytes).await {
-                        Ok(audio_url) => {
-                            if let Err(e) = state.telnyx_service.play_audio(&call_control_id, &audio_url).await {
-                                error!("❌ [CALL:{}] Error reproduciendo audio: {}", call_control_id, e);
-                            }
-                        }
-                        Err(e) => error!("❌ [CALL:{}] Error subiendo audio a S3: {}", call_control_id, e),
-                    }
-                }
-                Err(e) => error!("❌ [CALL:{}] Error generando audio con ElevenLabs: {}", call_control_id, e),
+            // ElevenLabs, S3 y reproducción en la cola de la llamada, en orden con las acciones del operador
+            let reply = CallAction::Reply { text: response_clean, filler: pending_filler, ticket };
+            if call_queue::enqueue(&state, &call_control_id, reply).await {
+                return (StatusCode::OK, Json(json!({"status": "queued"})));
             }
+            error!("❌ [CALL:{}] Error encolar respuesta TTS: cola cerrada", call_control_id);
         }
-    } else {
-        error!("⚠️ [CALL:{}] Sesión no encontrada", call_control_id);
+        Err(e) => error!("❌ [CALL:{}] Error generando respuesta Claude: {}", call_control_id, e),
     }
+    // Sin respuesta audible el cliente vuelve a tener la palabra
+    state.claim_call_state(&call_control_id, &[CallState::Thinking], CallState::Listening).await;
 
     (StatusCode::OK, Json(json!({"status": "handled"})))
 }
@@ -358,14 +459,8 @@ fn sanitize_plain(input: &str) -> String {
     cleaned.trim().to_string()
 }
 
-fn is_quick_reply_enabled() -> bool {
-    std::env::var("QUICK_REPLY_ENABLED")
-        .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
-        .unwrap_or(false)
-}
-
 async fn handle_transcription_partial(
-    _state: Arc<AppState>,
+    state: Arc<AppState>,
     payload: serde_json::Value,
 ) -> (StatusCode, Json<serde_json::Value>) {
     let call_control_id = payload["data"]["call_control_id"].as_str()
@@ -375,9 +470,28 @@ async fn handle_transcription_partial(
         .or_else(|| payload["data"]["payload"]["transcript"].as_str())
         .unwrap_or("");
     info!("🟡 [CALL:{}] Transcripción parcial: '{}'", call_control_id, transcript);
+    state.telnyx_transcripts
+        .push(call_control_id, TranscriptEvent::transcript(transcript, 1.0, false))
+        .await;
     (StatusCode::OK, Json(json!({"status": "partial"})))
 }
 
+/// Teclas marcadas por el cliente: llegan por webhook en ambos modos y solo se transcriben
+async fn handle_dtmf(
+    state: Arc<AppState>,
+    payload: serde_json::Value,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let data = if payload["data"]["payload"].is_object() { &payload["data"]["payload"] } else { &payload["data"] };
+    let (Some(call_control_id), Some(digit)) = (data["call_control_id"].as_str(), data["digit"].as_str()) else {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "Missing call_control_id or digit"})));
+    };
+
+    info!("🔢 [CALL:{}] DTMF recibido: {}", call_control_id, digit);
+    state.record_transcript(call_control_id, Speaker::Caller, TranscriptSource::Dtmf, digit, None).await;
+
+    (StatusCode::OK, Json(json!({"status": "handled"})))
+}
+
 async fn handle_hangup(
     state: Arc<AppState>,
     payload: serde_json::Value,
@@ -387,7 +501,16 @@ async fn handle_hangup(
         None => return (StatusCode::BAD_REQUEST, Json(json!({"error": "Missing call_control_id"}))),
     };
 
-    state.sessions.remove(&call_control_id);
+    state.end_call(&call_control_id, SessionEndReason::Hangup).await;
+
+    // El CDR pudo escribirse ya al cerrarse el stream: se completa con la causa de Telnyx
+    let data = if payload["data"]["payload"].is_object() { &payload["data"]["payload"] } else { &payload["data"] };
+    let cause = data["hangup_cause"].as_str().unwrap_or(SessionEndReason::Hangup.as_str()).to_string();
+    let from = data["from"].as_str().map(str::to_string);
+    let to = data["to"].as_str().map(str::to_string);
+    if let Err(e) = state.cdr.record_hangup(&call_control_id, cause, from, to).await {
+        error!("❌ [CALL:{}] Error actualizando CDR: {}", call_control_id, e);
+    }
 
     // ✅ Log corregido
     info!("☎️ [CALL:{}] Llamada finalizada", call_control_id);
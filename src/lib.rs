@@ -0,0 +1,5 @@
+//! Componentes sin dependencias del runtime del servidor (modelos y audio),
+//! expuestos como librería para reutilizarlos desde tests de integración y benchmarks.
+
+pub mod audio;
+pub mod models;
@@ -1,10 +1,12 @@
 mod handlers;
 mod services;
-mod models;
 mod utils;
 mod middleware;
 
+use telnyx_ai_service::{audio, models};
+
 use axum::{
+    extract::State,
     routing::{get, post},
     Router,
     http::StatusCode,
@@ -14,7 +16,7 @@ use std::sync::Arc;
 use tower_http::cors::CorsLayer;
 use tracing_subscriber;
 
-use crate::handlers::{call, webhook, test};
+use crate::handlers::{call, campaign, control, events, webhook, test};
 use crate::services::AppState;
 use crate::handlers::media_stream;
 
@@ -37,6 +39,15 @@ async fn main() {
     // Create app state
     let state = Arc::new(AppState::new().await);
 
+    // Limpieza periódica de audios TTS por llamada
+    state.audio_janitor.clone().spawn();
+
+    // Cierre de sesiones huérfanas (hangup perdido, streams sin webhook)
+    services::session_reaper::SessionReaper::from_env().spawn(state.clone());
+
+    // Precargar audios de relleno (solo si QUICK_REPLY_ENABLED)
+    tokio::spawn(services::filler::warm_up(state.clone()));
+
     // Define routes
     let app = Router::new()
         // Health check
@@ -46,6 +57,36 @@ async fn main() {
         .route("/api/call/initiate", post(call::initiate_call))
         .route("/api/call/batch", post(call::batch_calls))
         .route("/api/sessions/stats", get(call::session_stats))
+        .route("/api/cdr", get(call::call_records))
+        .route("/api/calls", get(call::list_calls))
+        .route("/api/calls/:call_control_id", get(call::call_detail))
+        // Eventos en vivo y transcripciones (datos del cliente): requieren ADMIN_API_KEY
+        .merge(
+            Router::new()
+                .route("/api/events", get(events::call_events))
+                .route("/api/calls/:call_control_id/transcript", get(call::call_transcript))
+                .route_layer(axum::middleware::from_fn(middleware::require_admin_key)),
+        )
+        // Control de llamadas en curso: requiere ADMIN_API_KEY
+        .merge(
+            Router::new()
+                .route("/api/calls/:call_control_id/hangup", post(control::hangup))
+                .route("/api/calls/:call_control_id/say", post(control::say))
+                .route("/api/calls/:call_control_id/phrase", post(control::phrase))
+                .route("/api/calls/:call_control_id/pause", post(control::pause))
+                .route("/api/calls/:call_control_id/resume", post(control::resume))
+                .route_layer(axum::middleware::from_fn(middleware::require_admin_key)),
+        )
+        // Campañas salientes: requieren ADMIN_API_KEY
+        .merge(
+            Router::new()
+                .route("/api/campaigns", get(campaign::list_campaigns).post(campaign::create_campaign))
+                .route("/api/campaigns/:campaign_id", get(campaign::campaign_detail))
+                .route("/api/campaigns/:campaign_id/pause", post(campaign::pause))
+                .route("/api/campaigns/:campaign_id/resume", post(campaign::resume))
+                .route("/api/campaigns/:campaign_id/cancel", post(campaign::cancel))
+                .route_layer(axum::middleware::from_fn(middleware::require_admin_key)),
+        )
         .route("/api/health", get(health_check))
         
         // Test routes
@@ -58,7 +99,7 @@ async fn main() {
         .route("/stream/media", axum::routing::get(media_stream::handle_media_stream))
         
         .layer(CorsLayer::permissive())
-        .with_state(state);
+        .with_state(state.clone());
 
     // Get port from env or use default
     let port = std::env::var("PORT")
@@ -77,6 +118,7 @@ async fn main() {
     );
 
     axum::serve(listener, app)
+        .with_graceful_shutdown(services::shutdown::on_signal(state))
         .await
         .expect("Server error");
 }
@@ -91,16 +133,30 @@ async fn root_handler() -> Json<serde_json::Value> {
             "initiateCall": "POST /api/call/initiate",
             "batchCalls": "POST /api/call/batch",
             "sessionStats": "GET /api/sessions/stats",
+            "activeCalls": "GET /api/calls?estado=&direccion=&telefono=&limit=&offset=",
+            "callDetail": "GET /api/calls/{call_control_id}",
+            "callControl": "POST /api/calls/{call_control_id}/{hangup|say|phrase|pause|resume}",
+            "events": "GET /api/events?call_control_id= (SSE)",
+            "callRecords": "GET /api/cdr?desde=&hasta=&telefono=",
+            "callTranscript": "GET /api/calls/{call_control_id}/transcript?format=json|text",
+            "campaigns": "GET|POST /api/campaigns",
+            "campaignControl": "POST /api/campaigns/{id}/{pause|resume|cancel}",
             "health": "GET /api/health"
         }
     }))
 }
 
-async fn health_check() -> (StatusCode, Json<serde_json::Value>) {
+/// Readiness: en drenaje responde 503 para que el balanceador deje de enviar tráfico
+async fn health_check(State(state): State<Arc<AppState>>) -> (StatusCode, Json<serde_json::Value>) {
+    let (status, label) = if state.lifecycle.is_draining() {
+        (StatusCode::SERVICE_UNAVAILABLE, "draining")
+    } else {
+        (StatusCode::OK, "healthy")
+    };
     (
-        StatusCode::OK,
+        status,
         Json(serde_json::json!({
-            "status": "healthy",
+            "status": label,
             "timestamp": chrono::Utc::now().to_rfc3339()
         }))
     )
@@ -1,9 +1,48 @@
 use axum::{
     extract::Request, // USAR ESTO en lugar de http::Request para Axum 0.7
+    http::StatusCode,
     middleware::Next,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
+    Json,
 };
-use tracing::info;
+use tracing::{info, warn};
+use crate::models::ErrorResponse;
+
+/// Header con la clave de administración (`ADMIN_API_KEY`) para la API de control
+pub const ADMIN_KEY_HEADER: &str = "x-admin-key";
+
+/// Protege rutas de administración con `ADMIN_API_KEY`. Sin la variable configurada
+/// las rutas quedan deshabilitadas.
+pub async fn require_admin_key(req: Request, next: Next) -> Response {
+    let expected = std::env::var("ADMIN_API_KEY").ok().filter(|key| !key.is_empty());
+    let Some(expected) = expected else {
+        warn!("🔒 {} rechazado: ADMIN_API_KEY no configurada", req.uri().path());
+        return reject(StatusCode::FORBIDDEN, "Control API disabled");
+    };
+
+    let provided = req.headers().get(ADMIN_KEY_HEADER).and_then(|v| v.to_str().ok());
+    if !provided.is_some_and(|key| constant_time_eq(key.as_bytes(), expected.as_bytes())) {
+        warn!("🔒 {} rechazado: clave de administración inválida", req.uri().path());
+        return reject(StatusCode::UNAUTHORIZED, "Invalid admin key");
+    }
+    next.run(req).await
+}
+
+fn reject(status: StatusCode, error: &str) -> Response {
+    (
+        status,
+        Json(ErrorResponse {
+            error: error.to_string(),
+            message: None,
+        }),
+    )
+        .into_response()
+}
+
+/// Comparación sin cortocircuito para no filtrar la clave por tiempos
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
 
 // Quitamos el genérico <B>. Ahora recibimos 'Request' directamente.
 pub async fn logging_middleware(
@@ -41,4 +80,16 @@ pub async fn logging_middleware(
     */
 
     response
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_keys_exactly() {
+        assert!(constant_time_eq(b"s3cret", b"s3cret"));
+        assert!(!constant_time_eq(b"s3cret", b"s3creT"));
+        assert!(!constant_time_eq(b"s3cret", b"s3cret2"));
+        assert!(!constant_time_eq(b"", b"s3cret"));
+    }
+}
@@ -1,5 +1,10 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// Identidad por defecto cuando la llamada no trae `client_state`
+pub const UNKNOWN_CALLER_NAME: &str = "Cliente";
+pub const UNKNOWN_CALLER_PHONE: &str = "desconocido";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InitiateCallRequest {
@@ -7,6 +12,75 @@ pub struct InitiateCallRequest {
     pub nombre: String,
     pub contexto: Option<String>,
     pub saludo: Option<String>,
+    /// Perfil de voz ElevenLabs para esta llamada (si no se envía, se usa el global)
+    #[serde(default)]
+    pub voz: Option<VoiceProfile>,
+    /// Ajustes de reconocimiento (Deepgram) para esta llamada
+    #[serde(default)]
+    pub stt: Option<SttOverrides>,
+    /// Consentimiento del cliente para grabar el audio de la llamada
+    #[serde(default)]
+    pub grabacion: Option<bool>,
+}
+
+/// Overrides de STT por llamada/persona. Lo omitido usa la configuración global
+/// (`DEEPGRAM_*`); `keywords` y `keyterms` se suman a los globales.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SttOverrides {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpointing_ms: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utterance_end_ms: Option<u32>,
+    /// Formato `palabra:intensificador`, p. ej. `"Macarena:2"` (modelos nova-2)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// Términos clave sin intensificador (modelos nova-3)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keyterms: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smart_format: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub punctuate: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub numerals: Option<bool>,
+}
+
+/// Ajustes de voz por llamada. Los campos omitidos toman el valor global del servicio.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VoiceProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stability: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similarity_boost: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_speaker_boost: Option<bool>,
+}
+
+impl VoiceProfile {
+    /// `stability`, `similarity_boost` y `style` van de 0.0 a 1.0 en ElevenLabs
+    pub fn validate(&self) -> Result<(), String> {
+        let settings = [
+            ("stability", self.stability),
+            ("similarity_boost", self.similarity_boost),
+            ("style", self.style),
+        ];
+        for (name, value) in settings {
+            if value.is_some_and(|v| !(0.0..=1.0).contains(&v)) {
+                return Err(format!("voz.{} must be between 0.0 and 1.0", name));
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +93,7 @@ pub struct CallResponse {
     pub call_control_id: String,
     pub call_id: String,
     pub status: String,
+    pub state: CallState,
     pub timestamp: DateTime<Utc>,
 }
 
@@ -58,6 +133,67 @@ pub struct ClientState {
     pub telefono: String,
     pub contexto: Option<String>,
     pub call_control_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voz: Option<VoiceProfile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stt: Option<SttOverrides>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grabacion: Option<bool>,
+}
+
+impl ClientState {
+    pub fn unknown(call_control_id: &str) -> Self {
+        Self {
+            nombre: UNKNOWN_CALLER_NAME.to_string(),
+            telefono: UNKNOWN_CALLER_PHONE.to_string(),
+            contexto: None,
+            call_control_id: Some(call_control_id.to_string()),
+            voz: None,
+            stt: None,
+            grabacion: None,
+        }
+    }
+
+    /// `client_state` tal como lo devuelve Telnyx (base64 del JSON que enviamos)
+    pub fn decode(b64: &str) -> Option<Self> {
+        let decoded = STANDARD.decode(b64).ok()?;
+        serde_json::from_str(std::str::from_utf8(&decoded).ok()?).ok()
+    }
+
+    /// Parámetros personalizados del mensaje `start` del media stream (`nombre`, `telefono`,
+    /// `contexto`, `grabacion`); tienen prioridad sobre el `client_state`
+    pub fn with_custom_parameters(mut self, params: &serde_json::Value) -> Self {
+        let text = |key: &str| params.get(key).and_then(|v| v.as_str()).filter(|v| !v.is_empty());
+        if let Some(nombre) = text("nombre") {
+            self.nombre = nombre.to_string();
+        }
+        if let Some(telefono) = text("telefono") {
+            self.telefono = telefono.to_string();
+        }
+        if let Some(contexto) = text("contexto") {
+            self.contexto = Some(contexto.to_string());
+        }
+        if let Some(grabacion) = params.get("grabacion") {
+            self.grabacion = grabacion.as_bool()
+                .or_else(|| grabacion.as_str().and_then(|v| v.parse().ok()))
+                .or(self.grabacion);
+        }
+        self
+    }
+}
+
+impl From<&InitiateCallRequest> for ClientState {
+    fn from(request: &InitiateCallRequest) -> Self {
+        Self {
+            nombre: request.nombre.clone(),
+            telefono: request.telefono.clone(),
+            contexto: request.contexto.clone(),
+            call_control_id: None,
+            voz: request.voz.clone(),
+            stt: request.stt.clone(),
+            grabacion: request.grabacion,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -69,11 +205,610 @@ pub struct SessionInfo {
     pub created_at: DateTime<Utc>,
     pub conversation_history: Vec<String>,
     pub transcription_started: bool,
+    #[serde(default)]
+    pub voz: Option<VoiceProfile>,
+    #[serde(default)]
+    pub stt: Option<SttOverrides>,
+    #[serde(default)]
+    pub grabacion: Option<bool>,
+    /// Transcripción de la llamada etiquetada por hablante
+    #[serde(default)]
+    pub transcript: Vec<TranscriptLine>,
+    /// Idioma de la conversación; `None` hasta detectarlo en el primer enunciado
+    #[serde(default)]
+    pub language: Option<Language>,
+    /// Turnos descartados por confianza baja en toda la llamada (líneas con mala calidad)
+    #[serde(default)]
+    pub low_confidence_turns: u32,
+    /// Turnos de confianza baja seguidos desde la última respuesta o aviso
+    #[serde(default)]
+    pub low_confidence_streak: u32,
+    #[serde(default)]
+    pub state: CallState,
+    #[serde(default)]
+    pub direction: CallDirection,
+    #[serde(default)]
+    pub answered_at: Option<DateTime<Utc>>,
+    /// Turnos, latencias y tokens acumulados para el CDR
+    #[serde(default)]
+    pub metrics: CallMetrics,
+    /// Pausada desde la API de control: se transcribe pero el bot no responde
+    #[serde(default)]
+    pub ai_paused: bool,
+    /// Último habla, respuesta o turno registrado; el reaper cierra sesiones inactivas
+    #[serde(default = "Utc::now")]
+    pub last_activity: DateTime<Utc>,
+}
+
+/// Idiomas que atiende el bot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    Es,
+    En,
+}
+
+impl Language {
+    /// Código ISO 639-1 (STT, caché de frases)
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::Es => "es",
+            Language::En => "en",
+        }
+    }
+
+    /// Locale para el TTS de Telnyx (`speak`)
+    pub fn locale(self) -> &'static str {
+        match self {
+            Language::Es => "es-MX",
+            Language::En => "en-US",
+        }
+    }
+
+    /// Acepta códigos simples o con región ("es", "es-419", "en-US")
+    pub fn from_code(code: &str) -> Option<Self> {
+        let base = code.split(['-', '_']).next().unwrap_or("").to_ascii_lowercase();
+        match base.as_str() {
+            "es" => Some(Language::Es),
+            "en" => Some(Language::En),
+            _ => None,
+        }
+    }
+}
+
+/// Fase de la llamada. Solo se avanza por transiciones válidas (`can_transition_to`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallState {
+    /// Llamada saliente creada, aún sin contestar
+    #[default]
+    Dialing,
+    /// Llamada entrante timbrando
+    Ringing,
+    Answered,
+    /// Sonando el saludo inicial
+    Greeting,
+    /// Esperando que hable el cliente
+    Listening,
+    /// Turno cerrado, generando respuesta
+    Thinking,
+    /// Sonando la respuesta del bot
+    Speaking,
+    Transferring,
+    Ended,
+}
+
+impl CallState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CallState::Dialing => "dialing",
+            CallState::Ringing => "ringing",
+            CallState::Answered => "answered",
+            CallState::Greeting => "greeting",
+            CallState::Listening => "listening",
+            CallState::Thinking => "thinking",
+            CallState::Speaking => "speaking",
+            CallState::Transferring => "transferring",
+            CallState::Ended => "ended",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        use CallState::*;
+        [Dialing, Ringing, Answered, Greeting, Listening, Thinking, Speaking, Transferring, Ended]
+            .into_iter()
+            .find(|state| state.as_str() == code)
+    }
+
+    /// Transiciones permitidas; cualquier estado vivo puede terminar
+    pub fn can_transition_to(self, next: CallState) -> bool {
+        use CallState::*;
+        if next == Ended {
+            return self != Ended;
+        }
+        matches!(
+            (self, next),
+            (Dialing, Ringing | Answered)
+                | (Ringing, Answered)
+                | (Answered, Greeting | Listening | Thinking)
+                | (Greeting, Listening | Thinking | Speaking | Transferring)
+                | (Listening, Thinking | Speaking | Transferring)
+                | (Thinking, Speaking | Listening | Transferring)
+                | (Speaking, Listening | Thinking | Transferring)
+                | (Transferring, Listening)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CallDirection {
+    /// Marcada por el servicio (`/api/call/initiate`, lotes)
+    #[default]
+    Outbound,
+    Inbound,
+}
+
+impl CallDirection {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CallDirection::Outbound => "outbound",
+            CallDirection::Inbound => "inbound",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "outbound" => Some(CallDirection::Outbound),
+            "inbound" => Some(CallDirection::Inbound),
+            _ => None,
+        }
+    }
+}
+
+/// Etapa del pipeline cuya latencia se mide por turno
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LatencyStage {
+    /// Fin del habla (VAD) → transcripción final
+    Stt,
+    /// Petición a Claude
+    Llm,
+    /// ElevenLabs + subida a S3
+    Tts,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CallMetrics {
+    pub turns: u32,
+    pub stt_latency_ms: Vec<u64>,
+    pub llm_latency_ms: Vec<u64>,
+    pub tts_latency_ms: Vec<u64>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl CallMetrics {
+    pub fn record_latency(&mut self, stage: LatencyStage, ms: u64) {
+        match stage {
+            LatencyStage::Stt => self.stt_latency_ms.push(ms),
+            LatencyStage::Llm => self.llm_latency_ms.push(ms),
+            LatencyStage::Tts => self.tts_latency_ms.push(ms),
+        }
+    }
+}
+
+/// Resumen de latencias de una etapa; `None` si no hubo muestras
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub avg_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+    pub max_ms: Option<u64>,
+}
+
+impl LatencyStats {
+    pub fn from_samples(samples: &[u64]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        let p95_index = ((sorted.len() as f64 * 0.95).ceil() as usize).clamp(1, sorted.len()) - 1;
+        Self {
+            avg_ms: Some(sorted.iter().sum::<u64>() / sorted.len() as u64),
+            p95_ms: Some(sorted[p95_index]),
+            max_ms: sorted.last().copied(),
+        }
+    }
+}
+
+/// Registro persistente de una llamada terminada (CDR)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallDetailRecord {
+    pub call_control_id: String,
+    pub direction: CallDirection,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub nombre: String,
+    pub telefono: String,
+    pub started_at: DateTime<Utc>,
+    pub answered_at: Option<DateTime<Utc>>,
+    pub ended_at: DateTime<Utc>,
+    /// `hangup_cause` de Telnyx, o el motivo interno si la sesión se cerró sin webhook
+    pub hangup_cause: String,
+    pub turns: u32,
+    pub stt_latency: LatencyStats,
+    pub llm_latency: LatencyStats,
+    pub tts_latency: LatencyStats,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// completed | no_answer | no_conversation | orphaned
+    pub outcome: String,
+}
+
+/// Filtros de `GET /api/cdr`. Fechas en RFC 3339 o `AAAA-MM-DD`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CdrQuery {
+    pub desde: Option<String>,
+    pub hasta: Option<String>,
+    /// Coincidencia parcial con el teléfono del cliente, origen o destino
+    pub telefono: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// `POST /api/calls/{id}/say`
+#[derive(Debug, Deserialize)]
+pub struct SayRequest {
+    pub texto: String,
+}
+
+/// `POST /api/calls/{id}/phrase`: clave del catálogo (`morning`, `still_there`, `filler_2`, ...)
+#[derive(Debug, Deserialize)]
+pub struct PhraseRequest {
+    pub frase: String,
+}
+
+/// Filtros de `GET /api/calls` (llamadas en curso)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CallsQuery {
+    /// Estado de la llamada (`listening`, `speaking`, ...)
+    pub estado: Option<String>,
+    /// `inbound` u `outbound`
+    pub direccion: Option<String>,
+    /// Coincidencia parcial con el teléfono del cliente
+    pub telefono: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// Fila de `GET /api/calls`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallSummary {
+    pub call_control_id: String,
+    pub nombre: String,
+    pub telefono: String,
+    pub direction: CallDirection,
+    pub state: CallState,
+    pub created_at: DateTime<Utc>,
+    pub answered_at: Option<DateTime<Utc>>,
+    /// Desde que se creó la sesión (marcado o timbre)
+    pub duration_secs: i64,
+    /// Sin actividad desde hace
+    pub idle_secs: i64,
+    pub turns: u32,
+    pub ai_paused: bool,
+    /// Última línea de la transcripción, de cualquiera de los dos
+    pub last_utterance: Option<TranscriptLine>,
+}
+
+impl CallSummary {
+    pub fn from_session(session: &SessionInfo, now: DateTime<Utc>) -> Self {
+        Self {
+            call_control_id: session.call_control_id.clone(),
+            nombre: session.nombre.clone(),
+            telefono: session.telefono.clone(),
+            direction: session.direction,
+            state: session.state,
+            created_at: session.created_at,
+            answered_at: session.answered_at,
+            duration_secs: (now - session.created_at).num_seconds().max(0),
+            idle_secs: (now - session.last_activity).num_seconds().max(0),
+            turns: session.metrics.turns,
+            ai_paused: session.ai_paused,
+            last_utterance: session.transcript.last().cloned(),
+        }
+    }
+}
+
+/// Página de `GET /api/calls`, más recientes primero
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallList {
+    /// Llamadas que cumplen los filtros, antes de paginar
+    pub total: usize,
+    pub limit: u32,
+    pub offset: u32,
+    pub calls: Vec<CallSummary>,
+}
+
+/// `GET /api/calls/{id}`: la sesión completa junto con los tiempos de la llamada
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallDetail {
+    pub duration_secs: i64,
+    pub idle_secs: i64,
+    #[serde(flatten)]
+    pub session: SessionInfo,
+}
+
+/// Evento en vivo de una llamada (`GET /api/events`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallEvent {
+    pub call_control_id: String,
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: CallEventKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CallEventKind {
+    /// Sesión creada al marcar o al timbrar una entrante
+    Started {
+        direction: CallDirection,
+        nombre: String,
+        telefono: String,
+    },
+    StateChanged { from: CallState, to: CallState },
+    /// Línea nueva de la transcripción (cliente, respuestas del bot, DTMF, operador)
+    Transcript { line: TranscriptLine },
+    Latency { stage: LatencyStage, ms: u64 },
+    /// IA pausada o reanudada desde la API de control
+    AiPaused { paused: bool },
+    /// Sesión cerrada (`hangup`, `stream_stopped`, `idle`, `max_lifetime`)
+    Ended { reason: String },
+}
+
+impl CallEventKind {
+    /// Nombre del evento SSE
+    pub fn name(&self) -> &'static str {
+        match self {
+            CallEventKind::Started { .. } => "started",
+            CallEventKind::StateChanged { .. } => "state_changed",
+            CallEventKind::Transcript { .. } => "transcript",
+            CallEventKind::Latency { .. } => "latency",
+            CallEventKind::AiPaused { .. } => "ai_paused",
+            CallEventKind::Ended { .. } => "ended",
+        }
+    }
+}
+
+/// Filtro de `GET /api/events`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EventsQuery {
+    pub call_control_id: Option<String>,
+}
+
+/// Quién habla en un segmento de la llamada
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Speaker {
+    /// Pista inbound: el cliente
+    Caller,
+    /// Pista outbound: lo que realmente sonó del bot en la línea
+    Bot,
+}
+
+impl Speaker {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Speaker::Caller => "caller",
+            Speaker::Bot => "bot",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "caller" => Some(Speaker::Caller),
+            "bot" => Some(Speaker::Bot),
+            _ => None,
+        }
+    }
+}
+
+/// De dónde sale una línea de la transcripción
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptSource {
+    /// Final del STT (cliente, o pista outbound del bot con `both_tracks`)
+    #[default]
+    SttFinal,
+    /// Tecla marcada por el cliente
+    Dtmf,
+    /// Relleno reproducido mientras llega la respuesta
+    Filler,
+    /// Respuesta de Claude que se llegó a reproducir
+    Llm,
+    /// Frase fija: saludo, aviso de silencio, petición de repetir, despedida
+    Phrase,
+    /// Texto dictado por un operador desde la API de control
+    Operator,
+}
+
+impl TranscriptSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TranscriptSource::SttFinal => "stt_final",
+            TranscriptSource::Dtmf => "dtmf",
+            TranscriptSource::Filler => "filler",
+            TranscriptSource::Llm => "llm",
+            TranscriptSource::Phrase => "phrase",
+            TranscriptSource::Operator => "operator",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "stt_final" => Some(TranscriptSource::SttFinal),
+            "dtmf" => Some(TranscriptSource::Dtmf),
+            "filler" => Some(TranscriptSource::Filler),
+            "llm" => Some(TranscriptSource::Llm),
+            "phrase" => Some(TranscriptSource::Phrase),
+            "operator" => Some(TranscriptSource::Operator),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptLine {
+    pub speaker: Speaker,
+    #[serde(default)]
+    pub source: TranscriptSource,
+    pub text: String,
+    /// Solo las líneas del STT traen confianza
+    pub confidence: Option<f64>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Transcripción completa de una llamada (`GET /api/calls/{id}/transcript`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallTranscript {
+    pub call_control_id: String,
+    /// `true` si la llamada sigue en curso
+    pub active: bool,
+    pub lines: Vec<TranscriptLine>,
+}
+
+impl CallTranscript {
+    /// Una línea por intervención: `[HH:MM:SS] Cliente: texto`
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for line in &self.lines {
+            let who = match line.speaker {
+                Speaker::Caller => "Cliente",
+                Speaker::Bot => "Bot",
+            };
+            let tag = match line.source {
+                TranscriptSource::Dtmf => " (DTMF)",
+                TranscriptSource::Filler => " (relleno)",
+                TranscriptSource::Operator => " (operador)",
+                _ => "",
+            };
+            text.push_str(&format!("[{}] {}{}: {}\n", line.timestamp.format("%H:%M:%S"), who, tag, line.text));
+        }
+        text
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TranscriptQuery {
+    /// `json` (por defecto) o `text`
+    pub format: Option<String>,
+}
+
+/// `POST /api/campaigns`: lista de contactos a marcar en segundo plano
+#[derive(Debug, Clone, Deserialize)]
+pub struct CampaignRequest {
+    pub nombre: Option<String>,
+    pub contactos: Vec<InitiateCallRequest>,
+    /// Llamadas simultáneas (por defecto `CAMPAIGN_MAX_CONCURRENT`)
+    pub max_concurrentes: Option<u32>,
+    /// Llamadas nuevas por segundo (por defecto `CAMPAIGN_CPS`)
+    pub cps: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CampaignStatus {
+    Running,
+    Paused,
+    /// No se marcan más contactos; las llamadas en curso siguen
+    Cancelled,
+    /// Todos los contactos marcados y sus llamadas terminadas
+    Completed,
+}
+
+impl CampaignStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CampaignStatus::Running => "running",
+            CampaignStatus::Paused => "paused",
+            CampaignStatus::Cancelled => "cancelled",
+            CampaignStatus::Completed => "completed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContactStatus {
+    Pending,
+    /// Marcado; la llamada todavía no se contestó
+    Dialing,
+    Answered,
+    /// Error al marcar, o la llamada terminó sin contestarse
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CampaignContact {
+    pub telefono: String,
+    pub nombre: String,
+    pub status: ContactStatus,
+    pub call_control_id: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CampaignCounts {
+    pub pending: usize,
+    pub dialing: usize,
+    pub answered: usize,
+    pub failed: usize,
+}
+
+impl CampaignCounts {
+    pub fn from_contacts(contacts: &[CampaignContact]) -> Self {
+        let mut counts = Self::default();
+        for contact in contacts {
+            match contact.status {
+                ContactStatus::Pending => counts.pending += 1,
+                ContactStatus::Dialing => counts.dialing += 1,
+                ContactStatus::Answered => counts.answered += 1,
+                ContactStatus::Failed => counts.failed += 1,
+            }
+        }
+        counts
+    }
+}
+
+/// Resumen de una campaña (`GET /api/campaigns`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CampaignSummary {
+    pub id: String,
+    pub nombre: Option<String>,
+    pub status: CampaignStatus,
+    pub created_at: DateTime<Utc>,
+    pub max_concurrentes: u32,
+    pub cps: u32,
+    pub total: usize,
+    pub counts: CampaignCounts,
+}
+
+/// `GET /api/campaigns/{id}`: resumen más el estado de cada contacto
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CampaignDetail {
+    #[serde(flatten)]
+    pub summary: CampaignSummary,
+    pub contactos: Vec<CampaignContact>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StatsResponse {
     pub active_sessions: usize,
+    /// Sesiones activas por estado de llamada
+    pub states: std::collections::BTreeMap<String, usize>,
     pub total_calls: u64,
     pub uptime_seconds: u64,
 }
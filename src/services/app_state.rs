@@ -1,18 +1,43 @@
 use std::sync::Arc;
 use std::collections::HashMap;
 use dashmap::DashMap;
-use tracing::{info, error};
-use crate::models::SessionInfo;
-use super::{TelnyxService, ClaudeService, S3Service, ElevenLabsService};
+use tracing::{info, error, warn};
+use tokio::sync::watch;
+use crate::models::{CallEventKind, CallResponse, CallState, ClientState, InitiateCallRequest, Language, LatencyStage, SessionInfo, Speaker, TranscriptSource, VoiceProfile};
+use super::{call_queue::CallAction, campaign::Campaigns, events::EventBus, shutdown::Lifecycle, SessionManager, TelnyxService, ClaudeService, S3Service, ElevenLabsService, AudioJanitor, SttProvider, TelnyxTranscriptSource, stt, turn::TurnCoordinator, silence::SilenceMonitor, SessionStore, session_store, session_reaper::SessionEndReason, cdr::{self, CdrStore}};
 
 pub struct AppState {
     pub telnyx_service: TelnyxService,
     pub claude_service: ClaudeService,
     pub elevenlabs_service: ElevenLabsService,
     pub s3_service: S3Service,
+    pub audio_janitor: Arc<AudioJanitor>,
+    pub stt_provider: Arc<dyn SttProvider>,
+    /// Transcripts de Telnyx que llegan por webhook para llamadas en modo media stream
+    pub telnyx_transcripts: Arc<TelnyxTranscriptSource>,
     pub greeting_urls: HashMap<String, String>,
-    pub quick_reply_urls: HashMap<String, String>,
-    pub sessions: Arc<DashMap<String, SessionInfo>>,
+    /// Caché en memoria de URLs de respuestas rápidas/fillers (evita un HEAD a S3 por uso)
+    pub quick_reply_urls: DashMap<String, String>,
+    /// Sesiones de llamada (memoria o Redis, según SESSION_STORE)
+    pub sessions: Arc<dyn SessionStore>,
+    /// Coordinador de turnos por llamada (una respuesta por turno del llamante)
+    pub turns: DashMap<String, Arc<TurnCoordinator>>,
+    /// VAD local y temporizador de silencio de las llamadas en modo media stream
+    pub silence_monitors: DashMap<String, Arc<SilenceMonitor>>,
+    /// Registros de llamadas terminadas (SQLite)
+    pub cdr: Arc<CdrStore>,
+    /// Cola de respuestas y acciones de operador por llamada (ver `call_queue`)
+    pub call_queues: DashMap<String, tokio::sync::mpsc::Sender<CallAction>>,
+    /// Audio que la cola de cada llamada espera que termine: `playback_id` y aviso de fin
+    pub playback_waiters: DashMap<String, (String, tokio::sync::oneshot::Sender<()>)>,
+    /// Campañas de llamadas salientes (ver `campaign`)
+    pub campaigns: Campaigns,
+    /// Drenaje y apagado del proceso (SIGTERM)
+    pub lifecycle: Lifecycle,
+    /// Eventos en vivo de las llamadas de esta réplica (`/api/events`)
+    pub events: EventBus,
+    /// Señal de cierre de los media streams abiertos en esta réplica
+    pub streams: DashMap<String, watch::Sender<bool>>,
     pub start_time: chrono::DateTime<chrono::Utc>,
     pub total_calls: std::sync::atomic::AtomicU64,
 }
@@ -23,33 +48,225 @@ impl AppState {
             .expect("S3 Service debe estar configurado para usar ElevenLabs");
 
         let elevenlabs_service = ElevenLabsService::new();
+        let audio_janitor = Arc::new(AudioJanitor::new(s3_service.clone()));
+        let telnyx_service = TelnyxService::new();
+        let telnyx_transcripts = Arc::new(TelnyxTranscriptSource::new(telnyx_service.clone()));
+        let stt_provider = stt::provider_from_env(telnyx_transcripts.clone());
+        let sessions = session_store::store_from_env().await
+            .expect("No se pudo inicializar el almacén de sesiones");
+        let cdr = CdrStore::from_env()
+            .expect("No se pudo abrir la base de datos de CDR");
 
         info!("✅ AppState inicializado con ElevenLabs + S3");
 
         Self {
-            telnyx_service: TelnyxService::new(),
+            telnyx_service,
             claude_service: ClaudeService::new(),
             elevenlabs_service,
             s3_service,
+            audio_janitor,
+            stt_provider,
+            telnyx_transcripts,
             greeting_urls: HashMap::new(),
-            quick_reply_urls: HashMap::new(),
-            sessions: Arc::new(DashMap::new()),
+            quick_reply_urls: DashMap::new(),
+            sessions,
+            cdr: Arc::new(cdr),
+            turns: DashMap::new(),
+            silence_monitors: DashMap::new(),
+            call_queues: DashMap::new(),
+            playback_waiters: DashMap::new(),
+            events: EventBus::new(),
+            campaigns: Campaigns::default(),
+            lifecycle: Lifecycle::new(),
+            streams: DashMap::new(),
             start_time: chrono::Utc::now(),
             total_calls: std::sync::atomic::AtomicU64::new(0),
         }
     }
 
-    pub async fn get_or_generate_greeting(&self, greeting_key: &str) -> Option<String> {
-        // Versión corta del saludo (3-4 segundos) para reducir latencia inicial
-        let text = match greeting_key {
-            "morning" => "Buenos días, Clínica La Wanda y Macarena. Hablas con María. ¿Con quién tengo el gusto?",
-            "afternoon" => "Buenas tardes, Clínica La Wanda y Macarena. Hablas con María. ¿Con quién tengo el gusto?",
-            "evening" => "Buenas noches, Clínica La Wanda y Macarena. Hablas con María. ¿Con quién tengo el gusto?",
-            _ => return None,
+    /// Detiene el monitor de silencio de la llamada (stream cerrado o hangup)
+    pub fn stop_silence_monitor(&self, call_id: &str) {
+        if let Some((_, monitor)) = self.silence_monitors.remove(call_id) {
+            monitor.stop();
+        }
+    }
+
+    /// Hubo audio del bot en la llamada: aplaza el aviso de silencio (no cancela uno pendiente)
+    pub fn touch_silence_monitor(&self, call_id: &str) {
+        if let Some(monitor) = self.silence_monitors.get(call_id) {
+            monitor.touch_bot();
+        }
+    }
+
+    /// Agrega una línea a la transcripción de la llamada (si sigue abierta)
+    pub async fn record_transcript(
+        &self,
+        call_id: &str,
+        speaker: Speaker,
+        source: TranscriptSource,
+        text: &str,
+        confidence: Option<f64>,
+    ) {
+        let recorded = self.sessions
+            .update(call_id, |session| {
+                SessionManager::add_transcript(session, speaker, source, text, confidence);
+                session.transcript.last().cloned()
+            })
+            .await;
+        if let Some(Some(line)) = recorded {
+            self.events.publish(call_id, CallEventKind::Transcript { line });
+        }
+    }
+
+    /// Guarda la latencia de una etapa para el CDR y la publica
+    pub async fn record_latency(&self, call_id: &str, stage: LatencyStage, ms: u64) {
+        self.sessions
+            .update(call_id, |session| SessionManager::record_latency(session, stage, ms))
+            .await;
+        self.events.publish(call_id, CallEventKind::Latency { stage, ms });
+    }
+
+    /// Registra el media stream de la llamada; el receptor pasa a `true` al cerrarla
+    pub fn register_stream(&self, call_id: &str) -> watch::Receiver<bool> {
+        let (tx, rx) = watch::channel(false);
+        self.streams.insert(call_id.to_string(), tx);
+        rx
+    }
+
+    /// Pide a las tareas del media stream que terminen (STT incluido)
+    pub fn close_stream(&self, call_id: &str) -> bool {
+        match self.streams.remove(call_id) {
+            Some((_, tx)) => {
+                let _ = tx.send(true);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Mueve la llamada a `next`; las transiciones inválidas se registran y se ignoran.
+    /// Devuelve `true` solo si el estado cambió.
+    pub async fn set_call_state(&self, call_id: &str, next: CallState) -> bool {
+        self.move_call_state(call_id, None, next).await
+    }
+
+    /// Como `set_call_state`, pero solo desde alguno de `from` (carreras esperables entre
+    /// webhook y stream: quien llega tarde no hace nada y no se registra como ilegal)
+    pub async fn claim_call_state(&self, call_id: &str, from: &[CallState], next: CallState) -> bool {
+        self.move_call_state(call_id, Some(from), next).await
+    }
+
+    async fn move_call_state(&self, call_id: &str, from: Option<&[CallState]>, next: CallState) -> bool {
+        let result = self.sessions.update(call_id, |session| {
+            let current = session.state;
+            if from.is_some_and(|from| !from.contains(&current)) {
+                return (current, Ok(false));
+            }
+            (current, SessionManager::transition(session, next))
+        }).await;
+
+        match result {
+            Some((previous, Ok(true))) => {
+                info!("🚦 [CALL:{}] Estado: {} → {}", call_id, previous.as_str(), next.as_str());
+                self.events.publish(call_id, CallEventKind::StateChanged { from: previous, to: next });
+                true
+            }
+            Some((previous, Err(_))) => {
+                warn!("⚠️ [CALL:{}] Transición ilegal ignorada: {} → {}", call_id, previous.as_str(), next.as_str());
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Marca un número (con media stream si `USE_MEDIA_STREAMS`) y abre su sesión en Dialing
+    pub async fn dial(&self, request: &InitiateCallRequest) -> anyhow::Result<CallResponse> {
+        // Por defecto usar WebSocket Media Streams para latencia óptima
+        let use_websocket = std::env::var("USE_MEDIA_STREAMS")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()
+            .unwrap_or(true);
+
+        let client_state = ClientState::from(request);
+        let response = if use_websocket {
+            self.telnyx_service
+                .initiate_call_with_stream(&request.telefono, &client_state)
+                .await?
+        } else {
+            self.telnyx_service
+                .initiate_call(&request.telefono, &client_state)
+                .await?
         };
 
-        // Nueva clave para forzar regenerar saludo corto
-        let s3_key = format!("audio/greeting_v2_{}.mp3", greeting_key);
+        self.total_calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        // La sesión existe desde que se marca (Dialing); el webhook y el stream la completan
+        let session = self.sessions
+            .open(SessionManager::from_client_state(response.call_control_id.clone(), &client_state))
+            .await;
+        self.events.publish(&session.call_control_id, CallEventKind::Started {
+            direction: session.direction,
+            nombre: session.nombre.clone(),
+            telefono: session.telefono.clone(),
+        });
+        Ok(response)
+    }
+
+    /// Cierra la llamada en esta réplica: borra la sesión y detiene sus tareas.
+    /// Devuelve la sesión si seguía abierta (solo quien la cierra la recibe).
+    pub async fn end_call(&self, call_id: &str, reason: SessionEndReason) -> Option<SessionInfo> {
+        let session = self.sessions.take(call_id).await;
+        if let Some((_, turns)) = self.turns.remove(call_id) {
+            turns.cancel();
+        }
+        self.stop_silence_monitor(call_id);
+        self.close_stream(call_id);
+        // Su worker descarta lo pendiente al no encontrar la sesión
+        self.call_queues.remove(call_id);
+        self.playback_waiters.remove(call_id);
+        // Devuelve el cupo a su campaña aunque la sesión ya no exista
+        self.campaigns.call_ended(call_id, session.as_ref().is_some_and(|s| s.answered_at.is_some()));
+
+        let mut session = session?;
+        let _ = SessionManager::transition(&mut session, CallState::Ended);
+        self.audio_janitor.mark_call_ended(call_id);
+        if session.low_confidence_turns > 0 {
+            warn!(
+                "🔉 [CALL:{}] {} turnos con confianza baja en la llamada",
+                call_id, session.low_confidence_turns
+            );
+        }
+        info!("☎️ [CALL:{}] Sesión cerrada ({})", call_id, reason.as_str());
+        self.events.publish(call_id, CallEventKind::Ended { reason: reason.as_str().to_string() });
+
+        if let Err(e) = self.cdr.insert(cdr::build_record(&session, reason, chrono::Utc::now())).await {
+            error!("❌ [CALL:{}] Error guardando CDR: {}", call_id, e);
+        }
+        if let Err(e) = self.cdr.insert_transcript(call_id, session.transcript.clone()).await {
+            error!("❌ [CALL:{}] Error guardando transcripción: {}", call_id, e);
+        }
+        Some(session)
+    }
+
+    pub fn turn_coordinator(&self, call_id: &str) -> Arc<TurnCoordinator> {
+        self.turns
+            .entry(call_id.to_string())
+            .or_insert_with(|| Arc::new(TurnCoordinator::from_env()))
+            .clone()
+    }
+
+    pub async fn get_or_generate_greeting(
+        &self,
+        greeting_key: &str,
+        voz: Option<&VoiceProfile>,
+    ) -> Option<String> {
+        let text = greeting_text(greeting_key)?;
+
+        // Nueva clave para forzar regenerar saludo corto; el sufijo separa perfiles de voz
+        let s3_key = format!(
+            "audio/greeting_v2_{}{}.mp3",
+            greeting_key,
+            self.elevenlabs_service.cache_suffix(voz)
+        );
 
         // Verificar si ya existe en S3
         if self.s3_service.object_exists(&s3_key).await {
@@ -59,7 +276,7 @@ impl AppState {
         }
 
         // Si no existe, generar y subir
-        match self.elevenlabs_service.text_to_speech(text).await {
+        match self.elevenlabs_service.text_to_speech_with_voice(text, voz).await {
             Ok(bytes) => {
                 match self.s3_service.upload_audio(&s3_key, bytes).await {
                     Ok(url) => Some(url),
@@ -76,26 +293,46 @@ impl AppState {
         }
     }
 
-    pub async fn get_or_generate_quick_reply(&self, key: &str) -> Option<String> {
-        let text = match key {
-            "processing" => "Entendido, dame un segundo mientras preparo tu respuesta.",
-            _ => return None,
+    pub async fn get_or_generate_quick_reply(
+        &self,
+        key: &str,
+        voz: Option<&VoiceProfile>,
+        language: Language,
+    ) -> Option<String> {
+        let text = quick_reply_text(key, language)?;
+
+        // Las frases en español conservan la clave histórica para no regenerar la caché
+        let language_suffix = match language {
+            Language::Es => String::new(),
+            other => format!("_{}", other.code()),
         };
+        let s3_key = format!(
+            "audio/quick_{}{}{}.mp3",
+            key,
+            language_suffix,
+            self.elevenlabs_service.cache_suffix(voz)
+        );
 
-        let s3_key = format!("audio/quick_{}.mp3", key);
+        if let Some(url) = self.quick_reply_urls.get(&s3_key) {
+            return Some(url.clone());
+        }
 
         // Verificar si ya existe en S3
         if self.s3_service.object_exists(&s3_key).await {
             let url = self.s3_service.get_url(&s3_key).await;
             info!("♻️ Reutilizando respuesta rapida existente: {} -> {}", key, url);
+            self.quick_reply_urls.insert(s3_key, url.clone());
             return Some(url);
         }
 
         // Si no existe, generar y subir
-        match self.elevenlabs_service.text_to_speech(text).await {
+        match self.elevenlabs_service.text_to_speech_with_voice(text, voz).await {
             Ok(bytes) => {
                 match self.s3_service.upload_audio(&s3_key, bytes).await {
-                    Ok(url) => Some(url),
+                    Ok(url) => {
+                        self.quick_reply_urls.insert(s3_key, url.clone());
+                        Some(url)
+                    }
                     Err(e) => {
                         error!("❌ Error subiendo respuesta rapida a S3: {}", e);
                         None
@@ -109,3 +346,38 @@ impl AppState {
         }
     }
 }
+
+/// Versión corta del saludo (3-4 segundos) para reducir latencia inicial
+pub fn greeting_text(greeting_key: &str) -> Option<&'static str> {
+    match greeting_key {
+        "morning" => Some("Buenos días, Clínica La Wanda y Macarena. Hablas con María. ¿Con quién tengo el gusto?"),
+        "afternoon" => Some("Buenas tardes, Clínica La Wanda y Macarena. Hablas con María. ¿Con quién tengo el gusto?"),
+        "evening" => Some("Buenas noches, Clínica La Wanda y Macarena. Hablas con María. ¿Con quién tengo el gusto?"),
+        _ => None,
+    }
+}
+
+/// Texto de las respuestas rápidas (rellenos y avisos) por idioma
+pub fn quick_reply_text(key: &str, language: Language) -> Option<&'static str> {
+    match (language, key) {
+        (Language::Es, "processing") => Some("Entendido, dame un segundo mientras preparo tu respuesta."),
+        (Language::Es, "filler_1") => Some("Mmm, déjame revisar."),
+        (Language::Es, "filler_2") => Some("Claro, un momento."),
+        (Language::Es, "filler_3") => Some("Ya te confirmo."),
+        (Language::Es, "filler_4") => Some("Dale, dame un segundito."),
+        (Language::Es, "still_there") => Some("¿Sigues ahí?"),
+        (Language::Es, "repeat_please") => Some("¿Me repites, por favor?"),
+        (Language::Es, "silence_goodbye") => Some("Parece que no te escucho. Te llamamos más tarde, ¡hasta luego!"),
+        (Language::Es, "shutdown_goodbye") => Some("Tenemos que cortar la llamada por ahora. Te llamamos más tarde, ¡hasta luego!"),
+        (Language::En, "processing") => Some("Got it, give me a second while I check."),
+        (Language::En, "filler_1") => Some("Hmm, let me check."),
+        (Language::En, "filler_2") => Some("Sure, one moment."),
+        (Language::En, "filler_3") => Some("I'll confirm that right away."),
+        (Language::En, "filler_4") => Some("Okay, just a second."),
+        (Language::En, "still_there") => Some("Are you still there?"),
+        (Language::En, "repeat_please") => Some("Could you repeat that, please?"),
+        (Language::En, "silence_goodbye") => Some("I can't hear you. We'll call you back later, goodbye!"),
+        (Language::En, "shutdown_goodbye") => Some("We have to end the call for now. We'll call you back later, goodbye!"),
+        _ => None,
+    }
+}
@@ -0,0 +1,217 @@
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use tracing::{info, error, debug};
+use super::S3Service;
+
+/// Prefijo de los audios TTS generados por turno de conversación.
+/// Saludos (`audio/greeting_*`) y respuestas rápidas (`audio/quick_*`) son caché
+/// compartida y nunca se tocan.
+pub const RESPONSE_AUDIO_PREFIX: &str = "audio/response_";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeletionReason {
+    /// El objeto superó el periodo de retención
+    Retention,
+    /// La llamada colgó y ya pasó el periodo de gracia
+    Hangup,
+}
+
+#[derive(Debug, Default)]
+pub struct JanitorReport {
+    pub scanned: usize,
+    pub deleted: Vec<(String, DeletionReason)>,
+    pub failed: Vec<String>,
+}
+
+/// Limpieza periódica de audios TTS por llamada en S3
+pub struct AudioJanitor {
+    s3_service: S3Service,
+    retention: Duration,
+    hangup_grace: Duration,
+    interval: std::time::Duration,
+    enabled: bool,
+    /// call_control_id -> momento del hangup
+    ended_calls: DashMap<String, DateTime<Utc>>,
+}
+
+impl AudioJanitor {
+    pub fn new(s3_service: S3Service) -> Self {
+        let retention_hours = std::env::var("AUDIO_RETENTION_HOURS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(24);
+        let grace_secs = std::env::var("AUDIO_HANGUP_GRACE_SECS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(120);
+        let interval_secs = std::env::var("AUDIO_JANITOR_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(300)
+            .max(10);
+        let enabled = std::env::var("AUDIO_JANITOR_ENABLED")
+            .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+            .unwrap_or(true);
+
+        info!(
+            "🧹 Audio janitor: enabled={}, retención={}h, gracia post-hangup={}s, intervalo={}s",
+            enabled, retention_hours, grace_secs, interval_secs
+        );
+
+        Self {
+            s3_service,
+            retention: Duration::hours(retention_hours),
+            hangup_grace: Duration::seconds(grace_secs),
+            interval: std::time::Duration::from_secs(interval_secs),
+            enabled,
+            ended_calls: DashMap::new(),
+        }
+    }
+
+    /// Registrar el hangup para borrar sus audios cuando pase el periodo de gracia
+    pub fn mark_call_ended(&self, call_control_id: &str) {
+        if self.enabled {
+            self.ended_calls.insert(call_control_id.to_string(), Utc::now());
+        }
+    }
+
+    /// Lanza el loop de limpieza en segundo plano
+    pub fn spawn(self: Arc<Self>) {
+        if !self.enabled {
+            info!("⏸️ Audio janitor deshabilitado (AUDIO_JANITOR_ENABLED=false)");
+            return;
+        }
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            loop {
+                ticker.tick().await;
+                self.sweep().await;
+            }
+        });
+    }
+
+    pub async fn sweep(&self) -> JanitorReport {
+        let mut report = JanitorReport::default();
+        let now = Utc::now();
+
+        let objects = match self.s3_service.list_objects(RESPONSE_AUDIO_PREFIX).await {
+            Ok(objects) => objects,
+            Err(e) => {
+                error!("❌ [Janitor] Error listando audios: {}", e);
+                return report;
+            }
+        };
+        report.scanned = objects.len();
+
+        for object in objects {
+            let reason = match deletion_reason(
+                &object.key,
+                object.last_modified,
+                now,
+                self.retention,
+                self.hangup_grace,
+                &self.ended_calls,
+            ) {
+                Some(reason) => reason,
+                None => continue,
+            };
+
+            match self.s3_service.delete_object(&object.key).await {
+                Ok(()) => {
+                    debug!("🗑️ [Janitor] Borrado {} ({:?})", object.key, reason);
+                    report.deleted.push((object.key, reason));
+                }
+                Err(e) => {
+                    error!("❌ [Janitor] Error borrando {}: {}", object.key, e);
+                    report.failed.push(object.key);
+                }
+            }
+        }
+
+        // Olvidar llamadas cuyo periodo de gracia ya venció (sus audios se borraron arriba)
+        self.ended_calls.retain(|_, ended_at| now - *ended_at < self.hangup_grace);
+
+        if !report.deleted.is_empty() || !report.failed.is_empty() {
+            let deleted_keys: Vec<&str> = report.deleted.iter().map(|(k, _)| k.as_str()).collect();
+            info!(
+                "🧹 [Janitor] Revisados {} audios, borrados {} ({} fallidos): {:?}",
+                report.scanned,
+                report.deleted.len(),
+                report.failed.len(),
+                deleted_keys
+            );
+        } else {
+            debug!("🧹 [Janitor] Revisados {} audios, nada que borrar", report.scanned);
+        }
+
+        report
+    }
+}
+
+fn deletion_reason(
+    key: &str,
+    last_modified: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    retention: Duration,
+    hangup_grace: Duration,
+    ended_calls: &DashMap<String, DateTime<Utc>>,
+) -> Option<DeletionReason> {
+    if !key.starts_with(RESPONSE_AUDIO_PREFIX) {
+        return None;
+    }
+
+    if let Some(modified) = last_modified {
+        if now - modified >= retention {
+            return Some(DeletionReason::Retention);
+        }
+    }
+
+    // Formato: audio/response_{call_control_id}_{timestamp}.mp3
+    let rest = &key[RESPONSE_AUDIO_PREFIX.len()..];
+    let (call_id, _) = rest.rsplit_once('_')?;
+    let ended_at = ended_calls.get(call_id)?;
+    if now - *ended_at >= hangup_grace {
+        Some(DeletionReason::Hangup)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_per_call_responses_are_deleted() {
+        let now = Utc::now();
+        let old = Some(now - Duration::days(30));
+        let ended = DashMap::new();
+
+        for key in ["audio/greeting_v2_morning.mp3", "audio/quick_processing.mp3"] {
+            assert_eq!(deletion_reason(key, old, now, Duration::hours(24), Duration::seconds(60), &ended), None);
+        }
+        assert_eq!(
+            deletion_reason("audio/response_v3:abc_1700000000.mp3", old, now, Duration::hours(24), Duration::seconds(60), &ended),
+            Some(DeletionReason::Retention)
+        );
+    }
+
+    #[test]
+    fn hangup_waits_for_grace_period() {
+        let now = Utc::now();
+        let fresh = Some(now - Duration::seconds(5));
+        let ended = DashMap::new();
+        let key = "audio/response_v3:call_id-1_1700000000.mp3";
+
+        ended.insert("v3:call_id-1".to_string(), now - Duration::seconds(30));
+        assert_eq!(deletion_reason(key, fresh, now, Duration::hours(24), Duration::seconds(60), &ended), None);
+
+        ended.insert("v3:call_id-1".to_string(), now - Duration::seconds(90));
+        assert_eq!(
+            deletion_reason(key, fresh, now, Duration::hours(24), Duration::seconds(60), &ended),
+            Some(DeletionReason::Hangup)
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use tracing::{info, error, warn};
+use crate::audio::{wav, TELEPHONY_SAMPLE_RATE};
+use crate::models::Speaker;
+use super::S3Service;
+
+/// Prefijo de las grabaciones en el bucket (el janitor de audios TTS no las toca)
+pub const RECORDINGS_PREFIX: &str = "recordings/";
+
+/// Configuración global de la captura de audio de las llamadas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureConfig {
+    pub enabled: bool,
+    /// Guardar también la pista outbound (requiere `STREAM_TRACK=both_tracks`)
+    pub outbound: bool,
+    /// Solo grabar llamadas con `grabacion: true`; si no, basta con que no sea `false`
+    pub require_consent: bool,
+    /// Límite por pista para no acumular memoria en llamadas largas
+    pub max_seconds: u32,
+}
+
+impl CaptureConfig {
+    pub fn from_env() -> Self {
+        let env_bool = |name: &str, default: bool| {
+            std::env::var(name)
+                .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+                .unwrap_or(default)
+        };
+        Self {
+            enabled: env_bool("CALL_CAPTURE_ENABLED", false),
+            outbound: env_bool("CALL_CAPTURE_OUTBOUND", false),
+            require_consent: env_bool("CALL_CAPTURE_REQUIRE_CONSENT", true),
+            max_seconds: std::env::var("CALL_CAPTURE_MAX_SECONDS")
+                .ok()
+                .and_then(|v| v.parse::<u32>().ok())
+                .unwrap_or(600),
+        }
+    }
+
+    /// `consent` es el flag `grabacion` de la llamada
+    pub fn allows(&self, consent: Option<bool>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        match consent {
+            Some(consent) => consent,
+            None => !self.require_consent,
+        }
+    }
+}
+
+/// Audio μ-law crudo de una llamada, acumulado en memoria hasta el STOP del stream
+pub struct CallCapture {
+    call_id: String,
+    inbound: Vec<u8>,
+    outbound: Option<Vec<u8>>,
+    max_bytes: usize,
+    truncated: bool,
+}
+
+impl CallCapture {
+    /// `None` si la configuración o el consentimiento de la llamada no permiten grabar
+    pub fn start(call_id: &str, consent: Option<bool>) -> Option<Self> {
+        let config = CaptureConfig::from_env();
+        if !config.allows(consent) {
+            return None;
+        }
+        info!("⏺️ [CALL:{}] Captura de audio activa (outbound: {})", call_id, config.outbound);
+        Some(Self::new(call_id, &config))
+    }
+
+    fn new(call_id: &str, config: &CaptureConfig) -> Self {
+        Self {
+            call_id: call_id.to_string(),
+            inbound: Vec::new(),
+            outbound: config.outbound.then(Vec::new),
+            // μ-law: un byte por muestra
+            max_bytes: config.max_seconds as usize * TELEPHONY_SAMPLE_RATE as usize,
+            truncated: false,
+        }
+    }
+
+    pub fn push(&mut self, speaker: Speaker, ulaw: &[u8]) {
+        let buffer = match speaker {
+            Speaker::Caller => &mut self.inbound,
+            Speaker::Bot => match self.outbound.as_mut() {
+                Some(buffer) => buffer,
+                None => return,
+            },
+        };
+
+        let room = self.max_bytes.saturating_sub(buffer.len());
+        if room < ulaw.len() {
+            self.truncated = true;
+        }
+        buffer.extend_from_slice(&ulaw[..ulaw.len().min(room)]);
+    }
+
+    /// Sube las pistas capturadas como WAV μ-law y devuelve sus URLs
+    pub async fn finish(self, s3_service: &S3Service) -> Vec<String> {
+        if self.truncated {
+            warn!("⏺️ [CALL:{}] Captura truncada al límite CALL_CAPTURE_MAX_SECONDS", self.call_id);
+        }
+
+        let timestamp = chrono::Utc::now().timestamp();
+        let tracks = [("inbound", Some(self.inbound)), ("outbound", self.outbound)];
+        let mut urls = Vec::new();
+
+        for (track, audio) in tracks {
+            let Some(audio) = audio.filter(|a| !a.is_empty()) else {
+                continue;
+            };
+            let key = format!("{}{}_{}_{}.wav", RECORDINGS_PREFIX, self.call_id, track, timestamp);
+            let seconds = audio.len() as f64 / TELEPHONY_SAMPLE_RATE as f64;
+            match s3_service.upload_object(&key, wav::ulaw_to_wav(&audio), "audio/wav").await {
+                Ok(url) => {
+                    info!("⏺️ [CALL:{}] Grabación {} ({:.1}s): {}", self.call_id, track, seconds, url);
+                    urls.push(url);
+                }
+                Err(e) => error!("❌ [CALL:{}] Error subiendo grabación {}: {}", self.call_id, track, e),
+            }
+        }
+
+        urls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(require_consent: bool) -> CaptureConfig {
+        CaptureConfig { enabled: true, outbound: false, require_consent, max_seconds: 1 }
+    }
+
+    #[test]
+    fn consent_gates_capture() {
+        assert!(!CaptureConfig { enabled: false, ..config(false) }.allows(Some(true)));
+        assert!(config(true).allows(Some(true)));
+        assert!(!config(true).allows(None));
+        assert!(config(false).allows(None));
+        assert!(!config(false).allows(Some(false)));
+    }
+
+    #[test]
+    fn push_caps_each_track() {
+        let mut capture = CallCapture::new("c", &config(false));
+        capture.push(Speaker::Caller, &[0xFF; 6000]);
+        capture.push(Speaker::Caller, &[0xFF; 6000]);
+        capture.push(Speaker::Bot, &[0xFF; 160]);
+
+        assert_eq!(capture.inbound.len(), 8000);
+        assert!(capture.truncated);
+        assert!(capture.outbound.is_none());
+    }
+}
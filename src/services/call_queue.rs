@@ -0,0 +1,258 @@
+use std::sync::Arc;
+use std::time::Duration;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{info, error, debug, warn};
+use crate::models::{CallState, LatencyStage, Speaker, TranscriptSource, VoiceProfile};
+use super::{app_state, AppState, filler::FillerHandle, turn::TurnTicket};
+
+const QUEUE_CAPACITY: usize = 100;
+/// Máximo que se espera el `call.playback.ended` / `call.speak.ended` de un audio de la cola
+const PLAYBACK_END_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Lo que se hace sobre la línea de una llamada, en orden y sin solaparse: cada audio
+/// termina de sonar (o vence `PLAYBACK_END_TIMEOUT`) antes de pasar a la siguiente acción
+pub enum CallAction {
+    /// Respuesta del LLM a un turno; se descarta si el cliente ya cerró otro turno
+    Reply {
+        text: String,
+        filler: FillerHandle,
+        ticket: TurnTicket,
+    },
+    /// Texto dictado por un operador
+    Say { text: String },
+    /// Frase del catálogo (saludos y respuestas rápidas)
+    Phrase { key: String },
+    /// Colgar cuando termine lo anterior
+    Hangup,
+}
+
+impl CallAction {
+    fn name(&self) -> &'static str {
+        match self {
+            CallAction::Reply { .. } => "reply",
+            CallAction::Say { .. } => "say",
+            CallAction::Phrase { .. } => "phrase",
+            CallAction::Hangup => "hangup",
+        }
+    }
+}
+
+/// Encola `action` en la cola de la llamada, creándola (con su worker) en el primer uso.
+/// Devuelve `false` si la cola ya se cerró.
+pub async fn enqueue(state: &Arc<AppState>, call_id: &str, action: CallAction) -> bool {
+    let queue = state.call_queues
+        .entry(call_id.to_string())
+        .or_insert_with(|| {
+            let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+            tokio::spawn(run(state.clone(), call_id.to_string(), rx));
+            tx
+        })
+        .clone();
+    queue.send(action).await.is_ok()
+}
+
+/// Webhook `call.playback.ended` / `call.speak.ended`: si es el audio que espera la cola,
+/// la deja seguir con la siguiente acción
+pub fn playback_ended(state: &AppState, call_id: &str, payload: &serde_json::Value) {
+    let Some(playback_id) = playback_id(payload) else {
+        return;
+    };
+    if let Some((_, (_, ended))) = state.playback_waiters.remove_if(call_id, |_, (id, _)| *id == playback_id) {
+        let _ = ended.send(());
+    }
+}
+
+/// `playback_id` que la cola puso en el `client_state` del audio
+fn playback_id(payload: &serde_json::Value) -> Option<String> {
+    let b64 = payload["data"]["payload"]["client_state"].as_str()
+        .or_else(|| payload["data"]["client_state"].as_str())?;
+    let decoded = STANDARD.decode(b64).ok()?;
+    let json: serde_json::Value = serde_json::from_slice(&decoded).ok()?;
+    json["playback_id"].as_str().map(str::to_string)
+}
+
+/// `true` si `key` es una frase que se puede reproducir en la llamada
+pub fn is_catalog_phrase(key: &str, language: crate::models::Language) -> bool {
+    app_state::greeting_text(key).is_some() || app_state::quick_reply_text(key, language).is_some()
+}
+
+async fn run(state: Arc<AppState>, call_id: String, mut actions: mpsc::Receiver<CallAction>) {
+    while let Some(action) = actions.recv().await {
+        // La llamada pudo cerrarse con acciones pendientes
+        let Some(session) = state.sessions.get(&call_id).await else {
+            debug!("⏭️ [CALL:{}][Cola] Acción {} descartada: llamada cerrada", call_id, action.name());
+            continue;
+        };
+        let voz = session.voz.clone();
+        let language = session.language.unwrap_or_default();
+
+        match action {
+            CallAction::Reply { text, filler, ticket } => {
+                let turns = state.turn_coordinator(&call_id);
+                if !turns.is_current(ticket) {
+                    info!("⏭️ [CALL:{}][TTS] Respuesta obsoleta descartada", call_id);
+                    continue;
+                }
+                if session.ai_paused {
+                    info!("⏸️ [CALL:{}][TTS] IA en pausa, respuesta descartada", call_id);
+                    state.claim_call_state(&call_id, &[CallState::Thinking], CallState::Listening).await;
+                    continue;
+                }
+                let tts_started = std::time::Instant::now();
+                let mut spoke = false;
+                if let Some(url) = synthesize(&state, &call_id, &text, voz.as_ref()).await {
+                    let tts_ms = tts_started.elapsed().as_millis() as u64;
+                    state.record_latency(&call_id, LatencyStage::Tts, tts_ms).await;
+                    if filler.cancel() {
+                        debug!("🫧 [CALL:{}] Relleno ya sonó antes de la respuesta", call_id);
+                    }
+                    // Mientras se sintetizaba el cliente pudo cerrar otro turno
+                    if !turns.is_current(ticket) {
+                        info!("⏭️ [CALL:{}][TTS] Respuesta obsoleta descartada", call_id);
+                        continue;
+                    }
+                    spoke = play(&state, &call_id, &url, TranscriptSource::Llm, &text).await;
+                }
+                // La respuesta no llegó a sonar: el cliente vuelve a tener la palabra
+                if !spoke {
+                    state.claim_call_state(&call_id, &[CallState::Thinking], CallState::Listening).await;
+                }
+            }
+            CallAction::Say { text } => {
+                info!("🎛️ [CALL:{}][Operador] Decir: '{}'", call_id, text);
+                if let Some(url) = synthesize(&state, &call_id, &text, voz.as_ref()).await {
+                    play(&state, &call_id, &url, TranscriptSource::Operator, &text).await;
+                    continue;
+                }
+                // Sin ElevenLabs el mensaje del operador sale con la voz de Telnyx
+                warn!("⚠️ [CALL:{}][Operador] TTS no disponible, usando speak de Telnyx", call_id);
+                let (playback_id, ended) = expect_playback_end(&state, &call_id);
+                match state.telnyx_service.speak(&call_id, &text, language, Some(&playback_id)).await {
+                    Ok(_) => {
+                        spoken(&state, &call_id, TranscriptSource::Operator, &text).await;
+                        wait_playback_end(&state, &call_id, &playback_id, ended).await;
+                    }
+                    Err(e) => {
+                        state.playback_waiters.remove(&call_id);
+                        error!("❌ [CALL:{}][Operador] Error en speak: {}", call_id, e);
+                    }
+                }
+            }
+            CallAction::Phrase { key } => {
+                info!("🎛️ [CALL:{}][Operador] Frase: {}", call_id, key);
+                let (url, text) = match app_state::greeting_text(&key) {
+                    Some(text) => (state.get_or_generate_greeting(&key, voz.as_ref()).await, Some(text)),
+                    None => (
+                        state.get_or_generate_quick_reply(&key, voz.as_ref(), language).await,
+                        app_state::quick_reply_text(&key, language),
+                    ),
+                };
+                match (url, text) {
+                    (Some(url), Some(text)) => {
+                        play(&state, &call_id, &url, TranscriptSource::Phrase, text).await;
+                    }
+                    _ => error!("⚠️ [CALL:{}][Operador] No se pudo obtener la frase: {}", call_id, key),
+                }
+            }
+            CallAction::Hangup => {
+                info!("🎛️ [CALL:{}][Operador] Colgando", call_id);
+                if let Err(e) = state.telnyx_service.hangup(&call_id).await {
+                    error!("❌ [CALL:{}][Operador] Error colgando: {}", call_id, e);
+                }
+            }
+        }
+    }
+    debug!("🔚 [CALL:{}] Cola de la llamada finalizada", call_id);
+}
+
+/// ElevenLabs + subida a S3; devuelve la URL del audio
+async fn synthesize(state: &Arc<AppState>, call_id: &str, text: &str, voz: Option<&VoiceProfile>) -> Option<String> {
+    let audio_bytes = match state.elevenlabs_service.text_to_speech_with_voice(text, voz).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("❌ [CALL:{}] Error generando audio: {}", call_id, e);
+            return None;
+        }
+    };
+    let audio_key = format!("audio/response_{}_{}.mp3", call_id, chrono::Utc::now().timestamp());
+    match state.s3_service.upload_audio(&audio_key, audio_bytes).await {
+        Ok(url) => {
+            info!("🔊 [CALL:{}][TTS] Audio generado y subido: {}", call_id, url);
+            Some(url)
+        }
+        Err(e) => {
+            error!("❌ [CALL:{}] Error subiendo audio a S3: {}", call_id, e);
+            None
+        }
+    }
+}
+
+/// Reproduce el audio y espera a que termine de sonar
+async fn play(state: &Arc<AppState>, call_id: &str, url: &str, source: TranscriptSource, text: &str) -> bool {
+    let (playback_id, ended) = expect_playback_end(state, call_id);
+    match state.telnyx_service.play_audio_tagged(call_id, url, Some(&playback_id)).await {
+        Ok(_) => {
+            spoken(state, call_id, source, text).await;
+            wait_playback_end(state, call_id, &playback_id, ended).await;
+            true
+        }
+        Err(e) => {
+            state.playback_waiters.remove(call_id);
+            error!("❌ [CALL:{}] Error reproduciendo audio: {}", call_id, e);
+            false
+        }
+    }
+}
+
+/// Se registra antes de enviar el comando para no perder un fin que llegue enseguida
+fn expect_playback_end(state: &AppState, call_id: &str) -> (String, oneshot::Receiver<()>) {
+    let playback_id = uuid::Uuid::new_v4().to_string();
+    let (tx, rx) = oneshot::channel();
+    state.playback_waiters.insert(call_id.to_string(), (playback_id.clone(), tx));
+    (playback_id, rx)
+}
+
+async fn wait_playback_end(state: &AppState, call_id: &str, playback_id: &str, ended: oneshot::Receiver<()>) {
+    match tokio::time::timeout(PLAYBACK_END_TIMEOUT, ended).await {
+        Ok(Ok(())) => debug!("🔈 [CALL:{}][Cola] Audio terminado", call_id),
+        // `end_call` soltó la espera: la llamada ya no existe
+        Ok(Err(_)) => debug!("🔈 [CALL:{}][Cola] Llamada cerrada durante el audio", call_id),
+        Err(_) => {
+            warn!("⏰ [CALL:{}][Cola] Sin evento de fin de audio en {}s, se continúa", call_id, PLAYBACK_END_TIMEOUT.as_secs());
+            state.playback_waiters.remove_if(call_id, |_, (id, _)| id == playback_id);
+        }
+    }
+}
+
+/// El bot empezó a sonar en la línea
+async fn spoken(state: &Arc<AppState>, call_id: &str, source: TranscriptSource, text: &str) {
+    state.touch_silence_monitor(call_id);
+    state.record_transcript(call_id, Speaker::Bot, source, text, None).await;
+    state.set_call_state(call_id, CallState::Speaking).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Language;
+
+    #[test]
+    fn catalog_includes_greetings_and_quick_replies() {
+        assert!(is_catalog_phrase("morning", Language::En));
+        assert!(is_catalog_phrase("still_there", Language::Es));
+        assert!(is_catalog_phrase("filler_2", Language::En));
+        assert!(!is_catalog_phrase("hola", Language::Es));
+    }
+
+    #[test]
+    fn reads_playback_id_from_client_state() {
+        let client_state = STANDARD.encode(r#"{"interruptible":true,"playback_id":"p-1"}"#);
+        let payload = serde_json::json!({"data": {"payload": {"client_state": client_state}}});
+        assert_eq!(playback_id(&payload).as_deref(), Some("p-1"));
+
+        let untagged = STANDARD.encode(r#"{"interruptible":true}"#);
+        assert_eq!(playback_id(&serde_json::json!({"data": {"client_state": untagged}})), None);
+        assert_eq!(playback_id(&serde_json::json!({"data": {}})), None);
+    }
+}
@@ -0,0 +1,357 @@
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
+use dashmap::DashMap;
+use governor::{Quota, RateLimiter};
+use tokio::sync::{watch, OwnedSemaphorePermit, Semaphore};
+use tracing::{info, warn};
+use crate::models::{
+    CampaignContact, CampaignCounts, CampaignDetail, CampaignRequest, CampaignStatus,
+    CampaignSummary, ContactStatus, InitiateCallRequest,
+};
+use super::{session_reaper, AppState};
+
+/// Campañas de llamadas salientes de esta réplica (en memoria). Una campaña vive en la
+/// réplica que la creó: si el cierre de una de sus llamadas lo procesa otra, el reaper
+/// devuelve el cupo al ver que la sesión ya no existe.
+#[derive(Default)]
+pub struct Campaigns {
+    campaigns: DashMap<String, Arc<Campaign>>,
+    /// Llamada en curso → su contacto; al quitar la entrada se libera el cupo de concurrencia
+    calls: DashMap<String, CampaignCall>,
+}
+
+struct CampaignCall {
+    campaign: Arc<Campaign>,
+    contact: usize,
+    _slot: OwnedSemaphorePermit,
+}
+
+pub struct Campaign {
+    id: String,
+    nombre: Option<String>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    max_concurrent: u32,
+    cps: u32,
+    requests: Vec<InitiateCallRequest>,
+    contacts: Mutex<Vec<CampaignContact>>,
+    status: watch::Sender<CampaignStatus>,
+    /// Cuándo pasó a `cancelled`/`completed`; a partir de ahí cuenta la retención
+    finished_at: Mutex<Option<chrono::DateTime<chrono::Utc>>>,
+}
+
+impl Campaign {
+    pub fn summary(&self) -> CampaignSummary {
+        let contacts = self.contacts.lock().unwrap();
+        CampaignSummary {
+            id: self.id.clone(),
+            nombre: self.nombre.clone(),
+            status: *self.status.borrow(),
+            created_at: self.created_at,
+            max_concurrentes: self.max_concurrent,
+            cps: self.cps,
+            total: contacts.len(),
+            counts: CampaignCounts::from_contacts(&contacts),
+        }
+    }
+
+    pub fn detail(&self) -> CampaignDetail {
+        CampaignDetail {
+            summary: self.summary(),
+            contactos: self.contacts.lock().unwrap().clone(),
+        }
+    }
+
+    /// Cambia el estado solo si el actual está en `from`
+    pub fn transition(&self, from: &[CampaignStatus], to: CampaignStatus) -> bool {
+        let changed = self.status.send_if_modified(|status| {
+            if !from.contains(status) {
+                return false;
+            }
+            *status = to;
+            true
+        });
+        if changed && matches!(to, CampaignStatus::Cancelled | CampaignStatus::Completed) {
+            *self.finished_at.lock().unwrap() = Some(chrono::Utc::now());
+        }
+        changed
+    }
+
+    fn set_contact(&self, index: usize, status: ContactStatus, call_id: Option<String>, error: Option<String>) {
+        let mut contacts = self.contacts.lock().unwrap();
+        let contact = &mut contacts[index];
+        contact.status = status;
+        if call_id.is_some() {
+            contact.call_control_id = call_id;
+        }
+        contact.error = error;
+    }
+}
+
+/// Tope de llamadas simultáneas por campaña, muy por debajo de `Semaphore::MAX_PERMITS`
+pub const MAX_CONCURRENT_LIMIT: u32 = 1000;
+
+/// `CAMPAIGN_MAX_CONCURRENT` y `CAMPAIGN_CPS`: límites si la campaña no trae los suyos
+pub fn default_limits() -> (u32, u32) {
+    let max_concurrent = std::env::var("CAMPAIGN_MAX_CONCURRENT")
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(10);
+    let cps = std::env::var("CAMPAIGN_CPS")
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(1);
+    (max_concurrent, cps)
+}
+
+/// `CAMPAIGN_RETENTION_SECS`: cuánto se conserva una campaña terminada o cancelada
+pub fn retention() -> chrono::Duration {
+    let secs = std::env::var("CAMPAIGN_RETENTION_SECS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(86400);
+    chrono::Duration::seconds(secs)
+}
+
+impl Campaigns {
+    pub fn get(&self, id: &str) -> Option<Arc<Campaign>> {
+        self.campaigns.get(id).map(|c| c.clone())
+    }
+
+    /// Más recientes primero
+    pub fn list(&self) -> Vec<CampaignSummary> {
+        let mut campaigns: Vec<CampaignSummary> = self.campaigns.iter().map(|c| c.summary()).collect();
+        campaigns.sort_by_key(|c| std::cmp::Reverse(c.created_at));
+        campaigns
+    }
+
+    /// Olvida las campañas terminadas hace más de `retention`; devuelve cuántas quitó.
+    /// Sus llamadas aún en curso conservan la campaña hasta colgar.
+    pub fn evict_finished(&self, retention: chrono::Duration, now: chrono::DateTime<chrono::Utc>) -> usize {
+        let before = self.campaigns.len();
+        self.campaigns.retain(|_, campaign| {
+            campaign.finished_at.lock().unwrap().is_none_or(|finished| now - finished < retention)
+        });
+        before - self.campaigns.len()
+    }
+
+    /// Llamadas de campaña en curso en esta réplica
+    pub fn call_ids(&self) -> Vec<String> {
+        self.calls.iter().map(|entry| entry.key().clone()).collect()
+    }
+
+    /// Webhook `call.answered` de una llamada de campaña
+    pub fn call_answered(&self, call_id: &str) {
+        if let Some(call) = self.calls.get(call_id) {
+            call.campaign.set_contact(call.contact, ContactStatus::Answered, None, None);
+        }
+    }
+
+    /// La llamada terminó: libera su cupo y, si nunca se contestó, marca el contacto como fallido
+    pub fn call_ended(&self, call_id: &str, answered: bool) {
+        let Some((_, call)) = self.calls.remove(call_id) else {
+            return;
+        };
+        let mut contacts = call.campaign.contacts.lock().unwrap();
+        let contact = &mut contacts[call.contact];
+        if answered {
+            contact.status = ContactStatus::Answered;
+        } else if contact.status == ContactStatus::Dialing {
+            contact.status = ContactStatus::Failed;
+            contact.error = Some("no_answer".to_string());
+        }
+    }
+}
+
+/// Crea la campaña y arranca su marcador en segundo plano
+pub fn start(state: &Arc<AppState>, request: CampaignRequest, max_concurrent: u32, cps: u32) -> Arc<Campaign> {
+    let contacts = request.contactos.iter()
+        .map(|c| CampaignContact {
+            telefono: c.telefono.clone(),
+            nombre: c.nombre.clone(),
+            status: ContactStatus::Pending,
+            call_control_id: None,
+            error: None,
+        })
+        .collect();
+    let (status, _) = watch::channel(CampaignStatus::Running);
+    let campaign = Arc::new(Campaign {
+        id: uuid::Uuid::new_v4().to_string(),
+        nombre: request.nombre,
+        created_at: chrono::Utc::now(),
+        max_concurrent,
+        cps,
+        requests: request.contactos,
+        contacts: Mutex::new(contacts),
+        status,
+        finished_at: Mutex::new(None),
+    });
+    state.campaigns.campaigns.insert(campaign.id.clone(), campaign.clone());
+    info!(
+        "📣 [Campaña:{}] Creada: {} contactos, {} simultáneas, {} cps",
+        campaign.id, campaign.requests.len(), max_concurrent, cps
+    );
+    tokio::spawn(run(state.clone(), campaign.clone()));
+    campaign
+}
+
+async fn run(state: Arc<AppState>, campaign: Arc<Campaign>) {
+    let cps = NonZeroU32::new(campaign.cps).unwrap_or(NonZeroU32::MIN);
+    // Sin ráfagas: las llamadas salen espaciadas 1/cps segundos
+    let limiter = RateLimiter::direct(Quota::per_second(cps).allow_burst(NonZeroU32::MIN));
+    let slots = Arc::new(Semaphore::new(campaign.max_concurrent as usize));
+    let mut status = campaign.status.subscribe();
+
+    let mut next = 0;
+    while next < campaign.requests.len() {
+        // En pausa se espera aquí; cancelada se deja de marcar
+        let Ok(current) = status.wait_for(|s| *s != CampaignStatus::Paused).await.map(|s| *s) else {
+            return;
+        };
+        if current != CampaignStatus::Running {
+            break;
+        }
+
+        let slot = tokio::select! {
+            slot = slots.clone().acquire_owned() => slot.expect("semáforo de campaña cerrado"),
+            _ = status.changed() => continue,
+        };
+        limiter.until_ready().await;
+
+        if state.lifecycle.is_draining() {
+            warn!("🚰 [Campaña:{}] Pausada por apagado del servicio", campaign.id);
+            campaign.transition(&[CampaignStatus::Running], CampaignStatus::Paused);
+            continue;
+        }
+        // Pudo pausarse o cancelarse mientras se esperaba cupo o turno
+        if *status.borrow_and_update() != CampaignStatus::Running {
+            continue;
+        }
+
+        dial(&state, &campaign, next, slot).await;
+        next += 1;
+    }
+
+    if *status.borrow() == CampaignStatus::Cancelled {
+        info!("🛑 [Campaña:{}] Cancelada", campaign.id);
+        return;
+    }
+    // Terminada cuando todas las llamadas marcadas devuelven su cupo
+    let _ = slots.acquire_many(campaign.max_concurrent).await;
+    if campaign.transition(&[CampaignStatus::Running, CampaignStatus::Paused], CampaignStatus::Completed) {
+        let counts = CampaignCounts::from_contacts(&campaign.contacts.lock().unwrap());
+        info!(
+            "🏁 [Campaña:{}] Completada: {} contestadas, {} fallidas",
+            campaign.id, counts.answered, counts.failed
+        );
+    }
+}
+
+async fn dial(state: &Arc<AppState>, campaign: &Arc<Campaign>, index: usize, slot: OwnedSemaphorePermit) {
+    let request = &campaign.requests[index];
+    campaign.set_contact(index, ContactStatus::Dialing, None, None);
+
+    match state.dial(request).await {
+        Ok(response) => {
+            let call_id = response.call_control_id;
+            info!("📞 [Campaña:{}][CALL:{}] Marcando a {}", campaign.id, call_id, request.telefono);
+            campaign.set_contact(index, ContactStatus::Dialing, Some(call_id.clone()), None);
+            state.campaigns.calls.insert(call_id.clone(), CampaignCall {
+                campaign: campaign.clone(),
+                contact: index,
+                _slot: slot,
+            });
+            // Un hangup inmediato pudo cerrar la sesión antes de registrar la llamada
+            if session_reaper::session_gone(state, &call_id).await {
+                state.campaigns.call_ended(&call_id, false);
+            }
+        }
+        Err(e) => {
+            warn!("❌ [Campaña:{}] Error marcando a {}: {}", campaign.id, request.telefono, e);
+            campaign.set_contact(index, ContactStatus::Failed, None, Some(e.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn campaign(contacts: usize) -> Arc<Campaign> {
+        let (status, _) = watch::channel(CampaignStatus::Running);
+        let contact = CampaignContact {
+            telefono: "3001234567".into(),
+            nombre: "Ana".into(),
+            status: ContactStatus::Pending,
+            call_control_id: None,
+            error: None,
+        };
+        Arc::new(Campaign {
+            id: "c1".into(),
+            nombre: None,
+            created_at: chrono::Utc::now(),
+            max_concurrent: 1,
+            cps: 1,
+            requests: Vec::new(),
+            contacts: Mutex::new(vec![contact; contacts]),
+            status,
+            finished_at: Mutex::new(None),
+        })
+    }
+
+    #[test]
+    fn ended_call_frees_slot_and_fails_unanswered_contact() {
+        let campaigns = Campaigns::default();
+        let campaign = campaign(2);
+        let slots = Arc::new(Semaphore::new(1));
+        for (index, call_id) in ["a", "b"].into_iter().enumerate() {
+            campaign.set_contact(index, ContactStatus::Dialing, Some(call_id.into()), None);
+        }
+        campaigns.calls.insert("a".into(), CampaignCall {
+            campaign: campaign.clone(),
+            contact: 0,
+            _slot: slots.clone().try_acquire_owned().unwrap(),
+        });
+        assert_eq!(slots.available_permits(), 0);
+
+        campaigns.call_answered("a");
+        campaigns.call_ended("a", false);
+        assert_eq!(slots.available_permits(), 1);
+
+        campaigns.calls.insert("b".into(), CampaignCall {
+            campaign: campaign.clone(),
+            contact: 1,
+            _slot: slots.clone().try_acquire_owned().unwrap(),
+        });
+        campaigns.call_ended("b", false);
+
+        let counts = campaign.summary().counts;
+        assert_eq!((counts.answered, counts.failed, counts.dialing), (1, 1, 0));
+    }
+
+    #[test]
+    fn transitions_only_from_allowed_states() {
+        let campaign = campaign(0);
+        assert!(!campaign.transition(&[CampaignStatus::Paused], CampaignStatus::Running));
+        assert!(campaign.transition(&[CampaignStatus::Running], CampaignStatus::Paused));
+        assert!(campaign.transition(&[CampaignStatus::Running, CampaignStatus::Paused], CampaignStatus::Cancelled));
+        assert!(!campaign.transition(&[CampaignStatus::Running, CampaignStatus::Paused], CampaignStatus::Cancelled));
+        assert_eq!(campaign.summary().status, CampaignStatus::Cancelled);
+    }
+
+    #[test]
+    fn evicts_only_campaigns_finished_before_retention() {
+        let campaigns = Campaigns::default();
+        let running = campaign(0);
+        let cancelled = campaign(0);
+        cancelled.transition(&[CampaignStatus::Running], CampaignStatus::Cancelled);
+        campaigns.campaigns.insert("running".into(), running);
+        campaigns.campaigns.insert("cancelled".into(), cancelled);
+
+        let retention = chrono::Duration::hours(1);
+        assert_eq!(campaigns.evict_finished(retention, chrono::Utc::now()), 0);
+        let later = chrono::Utc::now() + retention;
+        assert_eq!(campaigns.evict_finished(retention, later), 1);
+        assert!(campaigns.get("running").is_some());
+        assert!(campaigns.get("cancelled").is_none());
+    }
+}
@@ -0,0 +1,397 @@
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, Connection, Row};
+use tracing::info;
+use crate::models::{CallDetailRecord, CallDirection, CdrQuery, LatencyStats, SessionInfo, Speaker, TranscriptLine, TranscriptSource, UNKNOWN_CALLER_PHONE};
+use super::session_reaper::SessionEndReason;
+
+const DEFAULT_DATABASE_PATH: &str = "data/calls.db";
+const DEFAULT_LIMIT: u32 = 100;
+const MAX_LIMIT: u32 = 1000;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS call_records (
+    call_control_id TEXT PRIMARY KEY,
+    direction TEXT NOT NULL,
+    from_number TEXT,
+    to_number TEXT,
+    nombre TEXT NOT NULL,
+    telefono TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    answered_at TEXT,
+    ended_at TEXT NOT NULL,
+    hangup_cause TEXT NOT NULL,
+    turns INTEGER NOT NULL,
+    stt_avg_ms INTEGER, stt_p95_ms INTEGER, stt_max_ms INTEGER,
+    llm_avg_ms INTEGER, llm_p95_ms INTEGER, llm_max_ms INTEGER,
+    tts_avg_ms INTEGER, tts_p95_ms INTEGER, tts_max_ms INTEGER,
+    input_tokens INTEGER NOT NULL,
+    output_tokens INTEGER NOT NULL,
+    outcome TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_call_records_started_at ON call_records(started_at);
+CREATE INDEX IF NOT EXISTS idx_call_records_telefono ON call_records(telefono);
+CREATE TABLE IF NOT EXISTS transcript_lines (
+    call_control_id TEXT NOT NULL,
+    seq INTEGER NOT NULL,
+    speaker TEXT NOT NULL,
+    source TEXT NOT NULL,
+    text TEXT NOT NULL,
+    confidence REAL,
+    timestamp TEXT NOT NULL,
+    PRIMARY KEY (call_control_id, seq)
+);
+";
+
+const COLUMNS: &str = "call_control_id, direction, from_number, to_number, nombre, telefono, \
+    started_at, answered_at, ended_at, hangup_cause, turns, \
+    stt_avg_ms, stt_p95_ms, stt_max_ms, llm_avg_ms, llm_p95_ms, llm_max_ms, \
+    tts_avg_ms, tts_p95_ms, tts_max_ms, input_tokens, output_tokens, outcome";
+
+/// Registros y transcripciones de llamadas terminadas en SQLite local. Las consultas corren en
+/// `spawn_blocking` para no frenar el runtime con E/S de disco.
+pub struct CdrStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl CdrStore {
+    /// `path` puede ser `:memory:` (tests)
+    pub fn open(path: &str) -> anyhow::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn: Arc::new(Mutex::new(conn)) })
+    }
+
+    /// `CDR_DATABASE_PATH` (por defecto `data/calls.db`)
+    pub fn from_env() -> anyhow::Result<Self> {
+        let path = std::env::var("CDR_DATABASE_PATH").unwrap_or_else(|_| DEFAULT_DATABASE_PATH.to_string());
+        if let Some(parent) = std::path::Path::new(&path).parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let store = Self::open(&path)?;
+        info!("✅ CDR en SQLite: {}", path);
+        Ok(store)
+    }
+
+    async fn run<T, F>(&self, f: F) -> anyhow::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        let result = tokio::task::spawn_blocking(move || f(&conn.lock().unwrap())).await?;
+        Ok(result?)
+    }
+
+    pub async fn insert(&self, record: CallDetailRecord) -> anyhow::Result<()> {
+        self.run(move |conn| {
+            conn.execute(
+                &format!("INSERT OR REPLACE INTO call_records ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)", COLUMNS),
+                params![
+                    record.call_control_id,
+                    record.direction.as_str(),
+                    record.from,
+                    record.to,
+                    record.nombre,
+                    record.telefono,
+                    record.started_at,
+                    record.answered_at,
+                    record.ended_at,
+                    record.hangup_cause,
+                    record.turns,
+                    record.stt_latency.avg_ms,
+                    record.stt_latency.p95_ms,
+                    record.stt_latency.max_ms,
+                    record.llm_latency.avg_ms,
+                    record.llm_latency.p95_ms,
+                    record.llm_latency.max_ms,
+                    record.tts_latency.avg_ms,
+                    record.tts_latency.p95_ms,
+                    record.tts_latency.max_ms,
+                    record.input_tokens,
+                    record.output_tokens,
+                    record.outcome,
+                ],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Completa el CDR con los datos del webhook `call.hangup`, que puede llegar después de
+    /// que el media stream haya cerrado la sesión. Devuelve `false` si la llamada no tiene CDR.
+    pub async fn record_hangup(
+        &self,
+        call_id: &str,
+        cause: String,
+        from: Option<String>,
+        to: Option<String>,
+    ) -> anyhow::Result<bool> {
+        let call_id = call_id.to_string();
+        let updated = self.run(move |conn| {
+            conn.execute(
+                "UPDATE call_records SET hangup_cause = ?2, \
+                 from_number = COALESCE(?3, from_number), to_number = COALESCE(?4, to_number) \
+                 WHERE call_control_id = ?1",
+                params![call_id, cause, from, to],
+            )
+        })
+        .await?;
+        Ok(updated > 0)
+    }
+
+    /// Guarda la transcripción de una llamada terminada (reemplaza la anterior si la hubiera)
+    pub async fn insert_transcript(&self, call_id: &str, lines: Vec<TranscriptLine>) -> anyhow::Result<()> {
+        let call_id = call_id.to_string();
+        self.run(move |conn| {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute("DELETE FROM transcript_lines WHERE call_control_id = ?1", params![call_id])?;
+            {
+                let mut statement = tx.prepare(
+                    "INSERT INTO transcript_lines (call_control_id, seq, speaker, source, text, confidence, timestamp) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                )?;
+                for (seq, line) in lines.iter().enumerate() {
+                    statement.execute(params![
+                        call_id,
+                        seq as i64,
+                        line.speaker.as_str(),
+                        line.source.as_str(),
+                        line.text,
+                        line.confidence,
+                        line.timestamp,
+                    ])?;
+                }
+            }
+            tx.commit()
+        })
+        .await
+    }
+
+    /// En orden de la llamada; vacía si no hay transcripción guardada
+    pub async fn transcript(&self, call_id: &str) -> anyhow::Result<Vec<TranscriptLine>> {
+        let call_id = call_id.to_string();
+        self.run(move |conn| {
+            let mut statement = conn.prepare(
+                "SELECT speaker, source, text, confidence, timestamp FROM transcript_lines \
+                 WHERE call_control_id = ?1 ORDER BY seq",
+            )?;
+            let rows = statement.query_map(params![call_id], |row| {
+                Ok(TranscriptLine {
+                    speaker: Speaker::from_code(&row.get::<_, String>(0)?).unwrap_or(Speaker::Caller),
+                    source: TranscriptSource::from_code(&row.get::<_, String>(1)?).unwrap_or_default(),
+                    text: row.get(2)?,
+                    confidence: row.get(3)?,
+                    timestamp: row.get(4)?,
+                })
+            })?;
+            rows.collect()
+        })
+        .await
+    }
+
+    /// Más recientes primero
+    pub async fn query(&self, query: CdrQuery) -> anyhow::Result<Vec<CallDetailRecord>> {
+        let since = match query.desde.as_deref() {
+            Some(value) => Some(parse_bound(value, false).ok_or_else(|| anyhow::anyhow!("Fecha 'desde' inválida: {}", value))?),
+            None => None,
+        };
+        let until = match query.hasta.as_deref() {
+            Some(value) => Some(parse_bound(value, true).ok_or_else(|| anyhow::anyhow!("Fecha 'hasta' inválida: {}", value))?),
+            None => None,
+        };
+        let phone = query.telefono.filter(|t| !t.trim().is_empty()).map(|t| format!("%{}%", t.trim()));
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+        let offset = query.offset.unwrap_or(0);
+
+        self.run(move |conn| {
+            let mut statement = conn.prepare(&format!(
+                "SELECT {} FROM call_records \
+                 WHERE (?1 IS NULL OR started_at >= ?1) \
+                   AND (?2 IS NULL OR started_at < ?2) \
+                   AND (?3 IS NULL OR telefono LIKE ?3 OR from_number LIKE ?3 OR to_number LIKE ?3) \
+                 ORDER BY started_at DESC LIMIT ?4 OFFSET ?5",
+                COLUMNS
+            ))?;
+            let rows = statement.query_map(params![since, until, phone, limit, offset], read_record)?;
+            rows.collect()
+        })
+        .await
+    }
+}
+
+fn read_record(row: &Row) -> rusqlite::Result<CallDetailRecord> {
+    let latency = |base: usize| -> rusqlite::Result<LatencyStats> {
+        Ok(LatencyStats {
+            avg_ms: row.get(base)?,
+            p95_ms: row.get(base + 1)?,
+            max_ms: row.get(base + 2)?,
+        })
+    };
+    Ok(CallDetailRecord {
+        call_control_id: row.get(0)?,
+        direction: CallDirection::from_code(&row.get::<_, String>(1)?).unwrap_or_default(),
+        from: row.get(2)?,
+        to: row.get(3)?,
+        nombre: row.get(4)?,
+        telefono: row.get(5)?,
+        started_at: row.get(6)?,
+        answered_at: row.get(7)?,
+        ended_at: row.get(8)?,
+        hangup_cause: row.get(9)?,
+        turns: row.get(10)?,
+        stt_latency: latency(11)?,
+        llm_latency: latency(14)?,
+        tts_latency: latency(17)?,
+        input_tokens: row.get(20)?,
+        output_tokens: row.get(21)?,
+        outcome: row.get(22)?,
+    })
+}
+
+/// CDR de una sesión que se acaba de cerrar
+pub fn build_record(session: &SessionInfo, reason: SessionEndReason, ended_at: DateTime<Utc>) -> CallDetailRecord {
+    let own_number = std::env::var("TELNYX_PHONE_NUMBER").ok();
+    let customer = Some(session.telefono.clone()).filter(|t| t != UNKNOWN_CALLER_PHONE);
+    let (from, to) = match session.direction {
+        CallDirection::Outbound => (own_number, customer),
+        CallDirection::Inbound => (customer, own_number),
+    };
+    let metrics = &session.metrics;
+
+    CallDetailRecord {
+        call_control_id: session.call_control_id.clone(),
+        direction: session.direction,
+        from,
+        to,
+        nombre: session.nombre.clone(),
+        telefono: session.telefono.clone(),
+        started_at: session.created_at,
+        answered_at: session.answered_at,
+        ended_at,
+        hangup_cause: reason.as_str().to_string(),
+        turns: metrics.turns,
+        stt_latency: LatencyStats::from_samples(&metrics.stt_latency_ms),
+        llm_latency: LatencyStats::from_samples(&metrics.llm_latency_ms),
+        tts_latency: LatencyStats::from_samples(&metrics.tts_latency_ms),
+        input_tokens: metrics.input_tokens,
+        output_tokens: metrics.output_tokens,
+        outcome: outcome(session, reason).to_string(),
+    }
+}
+
+fn outcome(session: &SessionInfo, reason: SessionEndReason) -> &'static str {
+    match reason {
+        SessionEndReason::Idle | SessionEndReason::MaxLifetime => "orphaned",
+        SessionEndReason::Shutdown if session.answered_at.is_some() => "interrupted",
+        _ if session.answered_at.is_none() => "no_answer",
+        _ if session.metrics.turns == 0 => "no_conversation",
+        _ => "completed",
+    }
+}
+
+/// RFC 3339 o `AAAA-MM-DD`; una fecha sola como límite superior incluye todo ese día
+pub fn parse_bound(value: &str, upper: bool) -> Option<DateTime<Utc>> {
+    if let Ok(instant) = DateTime::parse_from_rfc3339(value) {
+        return Some(instant.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    let date = if upper { date.succ_opt()? } else { date };
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CallState, LatencyStage};
+    use crate::services::SessionManager;
+
+    fn finished_call(id: &str, telefono: &str, started_at: DateTime<Utc>) -> SessionInfo {
+        let mut session = SessionManager::create_session(id.into(), "Ana".into(), telefono.into());
+        session.created_at = started_at;
+        SessionManager::transition(&mut session, CallState::Answered).unwrap();
+        SessionManager::record_turn(&mut session);
+        for ms in [300, 500, 900] {
+            SessionManager::record_latency(&mut session, LatencyStage::Llm, ms);
+        }
+        session
+    }
+
+    #[test]
+    fn latency_stats_summarize_samples() {
+        assert_eq!(LatencyStats::from_samples(&[]), LatencyStats::default());
+        let stats = LatencyStats::from_samples(&(1..=20).map(|i| i * 100).collect::<Vec<_>>());
+        assert_eq!(stats.avg_ms, Some(1050));
+        assert_eq!(stats.p95_ms, Some(1900));
+        assert_eq!(stats.max_ms, Some(2000));
+    }
+
+    #[test]
+    fn outcome_reflects_how_the_call_went() {
+        let now = Utc::now();
+        let session = finished_call("a", "+57300", now);
+        assert_eq!(build_record(&session, SessionEndReason::Hangup, now).outcome, "completed");
+        assert_eq!(build_record(&session, SessionEndReason::Idle, now).outcome, "orphaned");
+
+        let unanswered = SessionManager::create_session("b".into(), "Ana".into(), "+57300".into());
+        assert_eq!(build_record(&unanswered, SessionEndReason::Hangup, now).outcome, "no_answer");
+    }
+
+    #[test]
+    fn date_bounds_accept_plain_dates() {
+        let start = parse_bound("2025-03-01", false).unwrap();
+        let end = parse_bound("2025-03-01", true).unwrap();
+        assert_eq!(end - start, chrono::Duration::days(1));
+        assert!(parse_bound("2025-03-01T10:00:00-05:00", false).is_some());
+        assert!(parse_bound("ayer", false).is_none());
+    }
+
+    #[tokio::test]
+    async fn stores_and_filters_records() {
+        let store = CdrStore::open(":memory:").unwrap();
+        let day = |d: u32| NaiveDate::from_ymd_opt(2025, 3, d).unwrap().and_hms_opt(15, 0, 0).unwrap().and_utc();
+
+        for (id, phone, started) in [("c1", "+573001112233", day(1)), ("c2", "+573009998877", day(2)), ("c3", "+573001112233", day(3))] {
+            let session = finished_call(id, phone, started);
+            store.insert(build_record(&session, SessionEndReason::StreamStopped, started)).await.unwrap();
+        }
+        assert!(store.record_hangup("c1", "normal_clearing".into(), None, Some("+573001112233".into())).await.unwrap());
+        assert!(!store.record_hangup("nope", "normal_clearing".into(), None, None).await.unwrap());
+
+        let all = store.query(CdrQuery::default()).await.unwrap();
+        assert_eq!(all.iter().map(|r| r.call_control_id.as_str()).collect::<Vec<_>>(), ["c3", "c2", "c1"]);
+        assert_eq!(all[2].hangup_cause, "normal_clearing");
+        assert_eq!(all[2].llm_latency.max_ms, Some(900));
+
+        let by_phone = store.query(CdrQuery { telefono: Some("1112233".into()), ..Default::default() }).await.unwrap();
+        assert_eq!(by_phone.len(), 2);
+
+        let by_date = store.query(CdrQuery { desde: Some("2025-03-02".into()), hasta: Some("2025-03-02".into()), ..Default::default() }).await.unwrap();
+        assert_eq!(by_date.len(), 1);
+        assert_eq!(by_date[0].call_control_id, "c2");
+
+        assert!(store.query(CdrQuery { desde: Some("ayer".into()), ..Default::default() }).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn stores_transcripts_in_order() {
+        let store = CdrStore::open(":memory:").unwrap();
+        let mut session = SessionManager::create_session("c1".into(), "Ana".into(), "+57300".into());
+        SessionManager::add_transcript(&mut session, Speaker::Bot, TranscriptSource::Phrase, "Buenos días", None);
+        SessionManager::add_transcript(&mut session, Speaker::Caller, TranscriptSource::SttFinal, "quiero una cita", Some(0.93));
+        SessionManager::add_transcript(&mut session, Speaker::Caller, TranscriptSource::Dtmf, "1", None);
+        SessionManager::add_transcript(&mut session, Speaker::Bot, TranscriptSource::Llm, "Claro, ¿para qué día?", None);
+
+        store.insert_transcript("c1", session.transcript.clone()).await.unwrap();
+        // Reintentar el cierre no duplica líneas
+        store.insert_transcript("c1", session.transcript.clone()).await.unwrap();
+
+        let lines = store.transcript("c1").await.unwrap();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[1].text, "quiero una cita");
+        assert_eq!(lines[1].confidence, Some(0.93));
+        assert_eq!(lines[2].source, TranscriptSource::Dtmf);
+        assert_eq!(lines[3].speaker, Speaker::Bot);
+        assert!(store.transcript("nope").await.unwrap().is_empty());
+    }
+}
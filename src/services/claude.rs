@@ -1,6 +1,7 @@
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tracing::{info, error};
+use crate::models::Language;
 
 #[derive(Clone)]
 pub struct ClaudeService {
@@ -41,6 +42,14 @@ struct Usage {
     output_tokens: i32,
 }
 
+/// Texto de la respuesta y consumo de tokens de la petición
+#[derive(Debug, Clone)]
+pub struct ClaudeReply {
+    pub text: String,
+    pub input_tokens: i32,
+    pub output_tokens: i32,
+}
+
 impl ClaudeService {
     pub fn new() -> Self {
         let api_key = std::env::var("ANTHROPIC_API_KEY")
@@ -61,13 +70,19 @@ impl ClaudeService {
         user_text: &str,
         nombre: &str,
         contexto: Option<&str>,
-    ) -> anyhow::Result<String> {
-        let system_prompt = self.get_system_prompt();
+        language: Language,
+    ) -> anyhow::Result<ClaudeReply> {
+        let system_prompt = self.get_system_prompt(language);
+
+        let (context_label, client_label, answer_hint, fallback) = match language {
+            Language::Es => ("Contexto", "Cliente", "Respuesta (60-80 chars, directo):", "Disculpa, ¿puedes repetir eso?"),
+            Language::En => ("Context", "Customer", "Answer (60-80 chars, direct):", "Sorry, could you repeat that?"),
+        };
 
         let short_prompt = if let Some(ctx) = contexto {
-            format!("Contexto: {}\nCliente ({}): {}", ctx, nombre, user_text)
+            format!("{}: {}\n{} ({}): {}", context_label, ctx, client_label, nombre, user_text)
         } else {
-            format!("Cliente ({}): {}", nombre, user_text)
+            format!("{} ({}): {}", client_label, nombre, user_text)
         };
 
         let request = MessageRequest {
@@ -79,8 +94,9 @@ impl ClaudeService {
                 MessageContent {
                     role: "user".to_string(),
                     content: format!(
-                        "{}\\n\\nRespuesta (60-80 chars, directo):",
-                        short_prompt
+                        "{}\\n\\n{}",
+                        short_prompt,
+                        answer_hint
                     ),
                 }
             ],
@@ -110,7 +126,7 @@ impl ClaudeService {
             .first()
             .and_then(|c| c.text.as_ref())
             .cloned()
-            .unwrap_or_else(|| "Disculpa, ¿puedes repetir eso?".to_string());
+            .unwrap_or_else(|| fallback.to_string());
 
         let cleaned = self.clean_response(&response_text);
         
@@ -125,14 +141,28 @@ impl ClaudeService {
         );
         info!("💬 [CLAUDE] Respuesta final: '{}'", cleaned);
 
-        Ok(cleaned)
+        Ok(ClaudeReply {
+            text: cleaned,
+            input_tokens: message_response.usage.input_tokens,
+            output_tokens: message_response.usage.output_tokens,
+        })
     }
 
     fn clean_response(&self, text: &str) -> String {
         sanitize_ascii(text)
     }
 
-    fn get_system_prompt(&self) -> String {
+    fn get_system_prompt(&self, language: Language) -> String {
+        if language == Language::En {
+            return "You are María, receptionist at LA WANDA Y MACARENA Veterinary Clinic in Colombia. The customer speaks English: answer in English, SHORT (60-80 chars).
+
+HOURS: Monday to Friday from eight in the morning to eight in the evening. Saturdays from nine in the morning to six in the evening. Sundays from ten in the morning to two in the afternoon.
+EMERGENCIES: 318 383 8417
+MONEY: Always spoken in Colombian pesos. Examples: 50.000 = \"fifty thousand pesos\", 150.000 = \"one hundred fifty thousand pesos\"
+
+STYLE: Natural, warm and direct. Use the customer's name if you know it.".to_string();
+        }
+
         "Eres María, recepcionista de Clínica Veterinaria LA WANDA Y MACARENA. Responde CORTO (60-80 chars).
 
 HORARIO: Lunes a viernes de ocho de la mañana a ocho de la noche. Sábados de nueve de la mañana a seis de la tarde. Domingos de diez de la mañana a dos de la tarde.
@@ -1,32 +1,156 @@
 use anyhow::{Result, anyhow};
+use async_trait::async_trait;
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message, tungstenite::client::IntoClientRequest};
 use futures_util::{SinkExt, StreamExt};
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::sync::mpsc;
+use tokio::time::Duration;
 use tracing::{info, error, warn, debug};
+use url::Url;
+use crate::models::SttOverrides;
+use super::stt::{SttProvider, SttStream, TranscriptEvent};
+
+const DEEPGRAM_LISTEN_URL: &str = "wss://api.deepgram.com/v1/listen";
+
+/// Conexiones supervisadas aún abiertas; el apagado espera a que lleguen a cero
+static OPEN_CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Conexiones a Deepgram abiertas en este proceso
+pub fn open_connections() -> usize {
+    OPEN_CONNECTIONS.load(Ordering::SeqCst)
+}
+
+struct ConnectionGuard;
+
+impl ConnectionGuard {
+    fn new() -> Self {
+        OPEN_CONNECTIONS.fetch_add(1, Ordering::SeqCst);
+        Self
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        OPEN_CONNECTIONS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
 
 #[derive(Clone)]
 pub struct DeepgramWebSocket {
-    api_key: String,
+    api_key: Option<String>,
+    config: DeepgramConfig,
 }
 
-#[derive(Debug, Serialize)]
-struct DeepgramConfig {
-    encoding: String,
-    sample_rate: u32,
-    channels: u16,
-    language: String,
-    model: String,
-    interim_results: bool,
-    endpointing: u32,
-    utterance_end_ms: u32,
-    vad_turnoff: u32,
+/// Parámetros de `/v1/listen`. El formato de audio es fijo (μ-law 8 kHz mono de Telnyx);
+/// el resto se configura con `DEEPGRAM_*` y se puede sobrescribir por llamada.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeepgramConfig {
+    pub language: String,
+    pub model: String,
+    pub endpointing_ms: u32,
+    pub utterance_end_ms: u32,
+    pub smart_format: bool,
+    pub punctuate: bool,
+    pub numerals: bool,
+    pub keywords: Vec<String>,
+    pub keyterms: Vec<String>,
+}
+
+impl DeepgramConfig {
+    pub fn from_env() -> Self {
+        let env_u32 = |name: &str, default: u32| {
+            std::env::var(name).ok().and_then(|v| v.parse::<u32>().ok()).unwrap_or(default)
+        };
+        let env_bool = |name: &str| {
+            std::env::var(name)
+                .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+                .unwrap_or(false)
+        };
+        let env_list = |name: &str| -> Vec<String> {
+            std::env::var(name)
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+
+        Self {
+            language: std::env::var("DEEPGRAM_LANGUAGE").unwrap_or_else(|_| "es".to_string()),
+            model: std::env::var("DEEPGRAM_MODEL").unwrap_or_else(|_| "nova-2".to_string()),
+            endpointing_ms: env_u32("DEEPGRAM_ENDPOINTING_MS", 200),      // 200ms de silencio para finalizar
+            utterance_end_ms: env_u32("DEEPGRAM_UTTERANCE_END_MS", 500), // Detectar fin de frase rápido
+            smart_format: env_bool("DEEPGRAM_SMART_FORMAT"),
+            punctuate: env_bool("DEEPGRAM_PUNCTUATE"),
+            numerals: env_bool("DEEPGRAM_NUMERALS"),
+            keywords: env_list("DEEPGRAM_KEYWORDS"),
+            keyterms: env_list("DEEPGRAM_KEYTERMS"),
+        }
+    }
+
+    /// Configuración efectiva de una llamada: global + overrides
+    pub fn with_overrides(&self, overrides: Option<&SttOverrides>) -> Self {
+        let Some(o) = overrides else {
+            return self.clone();
+        };
+
+        let mut keywords = self.keywords.clone();
+        keywords.extend(o.keywords.iter().cloned());
+        let mut keyterms = self.keyterms.clone();
+        keyterms.extend(o.keyterms.iter().cloned());
+
+        Self {
+            language: o.language.clone().unwrap_or_else(|| self.language.clone()),
+            model: o.model.clone().unwrap_or_else(|| self.model.clone()),
+            endpointing_ms: o.endpointing_ms.unwrap_or(self.endpointing_ms),
+            utterance_end_ms: o.utterance_end_ms.unwrap_or(self.utterance_end_ms),
+            smart_format: o.smart_format.unwrap_or(self.smart_format),
+            punctuate: o.punctuate.unwrap_or(self.punctuate),
+            numerals: o.numerals.unwrap_or(self.numerals),
+            keywords,
+            keyterms,
+        }
+    }
+
+    /// URL de conexión con todos los parámetros codificados
+    pub fn listen_url(&self) -> Result<Url> {
+        let mut url = Url::parse(DEEPGRAM_LISTEN_URL)?;
+        {
+            let mut query = url.query_pairs_mut();
+            query
+                .append_pair("encoding", "mulaw")
+                .append_pair("sample_rate", "8000")
+                .append_pair("channels", "1")
+                .append_pair("language", &self.language)
+                .append_pair("model", &self.model)
+                .append_pair("interim_results", "true")
+                .append_pair("endpointing", &self.endpointing_ms.to_string())
+                .append_pair("utterance_end_ms", &self.utterance_end_ms.to_string())
+                .append_pair("vad_events", "true") // Necesario para SpeechStarted
+                .append_pair("vad_turnoff", "300")  // VAD sensible
+                .append_pair("smart_format", &self.smart_format.to_string())
+                .append_pair("punctuate", &self.punctuate.to_string())
+                .append_pair("numerals", &self.numerals.to_string());
+            for keyword in &self.keywords {
+                query.append_pair("keywords", keyword);
+            }
+            for keyterm in &self.keyterms {
+                query.append_pair("keyterm", keyterm);
+            }
+        }
+        Ok(url)
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct DeepgramTranscript {
     pub channel: DeepgramChannel,
     pub is_final: bool,
+    #[serde(default)]
+    pub speech_final: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -38,56 +162,111 @@ pub struct DeepgramChannel {
 pub struct DeepgramAlternative {
     pub transcript: String,
     pub confidence: f64,
+    /// Solo con `language=multi`: idiomas detectados en el segmento, el dominante primero
+    #[serde(default)]
+    pub languages: Vec<String>,
 }
 
-impl DeepgramWebSocket {
-    pub fn new() -> Self {
-        let api_key = std::env::var("DEEPGRAM_API_KEY")
-            .expect("DEEPGRAM_API_KEY must be set");
+/// Configuración de reconexión del socket supervisado
+#[derive(Debug, Clone, Copy)]
+struct ReconnectPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    /// Máximo de audio (bytes μ-law, 8 por ms) que se guarda mientras no hay conexión
+    buffer_bytes: usize,
+}
 
-        Self { api_key }
+impl ReconnectPolicy {
+    fn from_env() -> Self {
+        let max_attempts = std::env::var("DEEPGRAM_RECONNECT_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(10);
+        let buffer_ms = std::env::var("DEEPGRAM_RECONNECT_BUFFER_MS")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(5000);
+
+        Self {
+            max_attempts,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(5),
+            buffer_bytes: buffer_ms * 8,
+        }
     }
+}
 
-    /// Conecta a Deepgram WebSocket y retorna canales para audio y transcripts
-    pub async fn connect(
-        &self,
-        call_id: String,
-    ) -> Result<(mpsc::Sender<Vec<u8>>, mpsc::Receiver<DeepgramTranscript>)> {
-        // Construir URL de conexión con parámetros
-        let config = DeepgramConfig {
-            encoding: "mulaw".to_string(),
-            sample_rate: 8000,
-            channels: 1,
-            language: "es".to_string(),
-            model: "nova-2".to_string(),
-            interim_results: true,
-            endpointing: 200,      // 200ms de silencio para finalizar
-            utterance_end_ms: 500, // Detectar fin de frase rápido
-            vad_turnoff: 300,      // VAD sensible
-        };
+/// Audio pendiente durante una reconexión; descarta lo más antiguo al llenarse
+struct AudioBacklog {
+    chunks: VecDeque<Vec<u8>>,
+    bytes: usize,
+    capacity: usize,
+    dropped: usize,
+}
+
+impl AudioBacklog {
+    fn new(capacity: usize) -> Self {
+        Self { chunks: VecDeque::new(), bytes: 0, capacity, dropped: 0 }
+    }
+
+    fn push(&mut self, chunk: Vec<u8>) {
+        self.bytes += chunk.len();
+        self.chunks.push_back(chunk);
+        while self.bytes > self.capacity {
+            match self.chunks.pop_front() {
+                Some(old) => {
+                    self.bytes -= old.len();
+                    self.dropped += old.len();
+                }
+                None => break,
+            }
+        }
+    }
+
+    fn drain(&mut self) -> Vec<Vec<u8>> {
+        self.bytes = 0;
+        self.chunks.drain(..).collect()
+    }
+}
+
+/// Por qué terminó una conexión individual con Deepgram
+enum SessionEnd {
+    /// La llamada cerró el canal de audio: fin normal
+    AudioClosed,
+    /// Nadie consume ya los transcripts
+    ConsumerGone,
+    /// El socket se cayó: hay que reconectar
+    Disconnected,
+}
+
+impl DeepgramWebSocket {
+    pub fn new() -> Self {
+        // Sin clave no se entra en pánico: cada `connect` falla y la llamada lo registra
+        let api_key = std::env::var("DEEPGRAM_API_KEY").ok();
+        if api_key.is_none() {
+            warn!("⚠️ DEEPGRAM_API_KEY no configurada");
+        }
 
-        let url = format!(
-            "wss://api.deepgram.com/v1/listen?encoding={}&sample_rate={}&channels={}&language={}&model={}&interim_results={}&endpointing={}&utterance_end_ms={}&vad_turnoff={}",
-            config.encoding,
-            config.sample_rate,
-            config.channels,
-            config.language,
-            config.model,
-            config.interim_results,
-            config.endpointing,
-            config.utterance_end_ms,
-            config.vad_turnoff
+        let config = DeepgramConfig::from_env();
+        info!(
+            "✅ Deepgram configurado: modelo {}, idioma {}, keywords {}, keyterms {}",
+            config.model, config.language, config.keywords.len(), config.keyterms.len()
         );
 
+        Self { api_key, config }
+    }
+
+    async fn open_socket(url: &str, api_key: &str, call_id: &str) -> Result<DeepgramSocket> {
         info!("🔌 [CALL:{}][WS->Deepgram] Conectando", call_id);
 
         // Crear request usando into_client_request() para headers correctos
         let mut request = url.into_client_request()
             .map_err(|e| anyhow!("Failed to build request: {}", e))?;
-        
+
         request.headers_mut().insert(
             "Authorization",
-            format!("Token {}", self.api_key)
+            format!("Token {}", api_key)
                 .parse()
                 .map_err(|e| anyhow!("Invalid header value: {}", e))?
         );
@@ -97,101 +276,398 @@ impl DeepgramWebSocket {
             .map_err(|e| anyhow!("WebSocket connection failed: {}", e))?;
 
         info!("✅ [CALL:{}][WS->Deepgram] Conectado", call_id);
+        Ok(ws_stream)
+    }
 
-        let (mut ws_write, mut ws_read) = ws_stream.split();
+    /// Conecta a Deepgram WebSocket y retorna canales para audio y transcripts.
+    /// La conexión queda supervisada: si el socket se cae se reconecta con backoff,
+    /// reenviando el audio recibido durante el corte, y los canales siguen siendo los mismos.
+    pub async fn connect(
+        &self,
+        call_id: String,
+        overrides: Option<&SttOverrides>,
+    ) -> Result<(mpsc::Sender<Vec<u8>>, mpsc::Receiver<TranscriptEvent>)> {
+        let api_key = self.api_key.clone()
+            .ok_or_else(|| anyhow!("DEEPGRAM_API_KEY no configurada"))?;
+        let config = self.config.with_overrides(overrides);
+        if overrides.is_some() {
+            debug!("🎛️ [CALL:{}][Deepgram] Configuración por llamada: {:?}", call_id, config);
+        }
+        let url = config.listen_url()?.to_string();
+
+        // La primera conexión es síncrona para que el llamador vea el error de inmediato
+        let ws_stream = Self::open_socket(&url, &api_key, &call_id).await?;
 
         // Canal para enviar audio a Deepgram
-        let (audio_tx, mut audio_rx) = mpsc::channel::<Vec<u8>>(100);
+        let (audio_tx, audio_rx) = mpsc::channel::<Vec<u8>>(100);
 
         // Canal para recibir transcripts de Deepgram
-        let (transcript_tx, transcript_rx) = mpsc::channel::<DeepgramTranscript>(100);
-
-        // Task única de envío: audio + ping keepalive, usando el mismo sink
-        let call_id_send = call_id.clone();
-        tokio::spawn(async move {
-            let mut ping_interval = tokio::time::interval(tokio::time::Duration::from_secs(25));
-            loop {
-                tokio::select! {
-                    maybe_audio = audio_rx.recv() => {
-                        match maybe_audio {
-                            Some(audio_data) => {
-                                if let Err(e) = ws_write.send(Message::Binary(audio_data)).await {
-                                    error!("❌ [CALL:{}] Error enviando audio a Deepgram: {}", call_id_send, e);
-                                    break;
-                                }
-                            }
-                            None => {
-                                debug!("📤 [CALL:{}] Canal de audio cerrado", call_id_send);
-                                // Enviar Close limpio
-                                let _ = ws_write.send(Message::Close(None)).await;
-                                break;
-                            }
-                        }
+        let (transcript_tx, transcript_rx) = mpsc::channel::<TranscriptEvent>(100);
+
+        tokio::spawn(supervise(
+            call_id,
+            url,
+            api_key,
+            ReconnectPolicy::from_env(),
+            ws_stream,
+            audio_rx,
+            transcript_tx,
+        ));
+
+        Ok((audio_tx, transcript_rx))
+    }
+}
+
+type DeepgramSocket = tokio_tungstenite::WebSocketStream<
+    tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+>;
+
+async fn supervise(
+    call_id: String,
+    url: String,
+    api_key: String,
+    policy: ReconnectPolicy,
+    initial: DeepgramSocket,
+    mut audio_rx: mpsc::Receiver<Vec<u8>>,
+    transcript_tx: mpsc::Sender<TranscriptEvent>,
+) {
+    let _connection = ConnectionGuard::new();
+    let mut backlog = AudioBacklog::new(policy.buffer_bytes);
+    let mut socket = Some(initial);
+    let mut reconnects: u32 = 0;
+
+    loop {
+        let ws_stream = match socket.take() {
+            Some(ws_stream) => ws_stream,
+            None => match reconnect(&call_id, &url, &api_key, policy, &mut audio_rx, &mut backlog).await {
+                Some(ws_stream) => {
+                    reconnects += 1;
+                    ws_stream
+                }
+                None => break,
+            },
+        };
+
+        match run_session(&call_id, ws_stream, &mut audio_rx, &transcript_tx, &mut backlog).await {
+            SessionEnd::AudioClosed | SessionEnd::ConsumerGone => break,
+            SessionEnd::Disconnected => {
+                warn!("⚠️ [CALL:{}][WS->Deepgram] Conexión perdida, reconectando", call_id);
+            }
+        }
+    }
+
+    info!("🔚 [CALL:{}] Cerrando conexión Deepgram (reconexiones: {})", call_id, reconnects);
+}
+
+/// Reintenta con backoff exponencial mientras guarda el audio que sigue llegando.
+/// Devuelve `None` si se agotan los intentos o la llamada terminó durante el corte.
+async fn reconnect(
+    call_id: &str,
+    url: &str,
+    api_key: &str,
+    policy: ReconnectPolicy,
+    audio_rx: &mut mpsc::Receiver<Vec<u8>>,
+    backlog: &mut AudioBacklog,
+) -> Option<DeepgramSocket> {
+    let mut backoff = policy.initial_backoff;
+
+    for attempt in 1..=policy.max_attempts {
+        let wait = tokio::time::sleep(backoff);
+        tokio::pin!(wait);
+        loop {
+            tokio::select! {
+                _ = &mut wait => break,
+                maybe_audio = audio_rx.recv() => match maybe_audio {
+                    Some(chunk) => backlog.push(chunk),
+                    None => {
+                        debug!("📤 [CALL:{}] Canal de audio cerrado durante la reconexión", call_id);
+                        return None;
                     }
-                    _ = ping_interval.tick() => {
-                        if let Err(e) = ws_write.send(Message::Ping(Vec::new())).await {
-                            warn!("⚠️ [CALL:{}] Ping Deepgram falló: {}", call_id_send, e);
-                            break;
-                        } else {
-                            debug!("📶 [CALL:{}] Ping Deepgram enviado", call_id_send);
+                },
+            }
+        }
+
+        match DeepgramWebSocket::open_socket(url, api_key, call_id).await {
+            Ok(ws_stream) => {
+                info!("🔁 [CALL:{}][WS->Deepgram] Reconectado (intento {}/{})", call_id, attempt, policy.max_attempts);
+                return Some(ws_stream);
+            }
+            Err(e) => {
+                warn!("⚠️ [CALL:{}][WS->Deepgram] Reintento {}/{} falló: {}", call_id, attempt, policy.max_attempts, e);
+                backoff = (backoff * 2).min(policy.max_backoff);
+            }
+        }
+    }
+
+    error!("❌ [CALL:{}][WS->Deepgram] Sin conexión tras {} intentos, la llamada queda sin STT", call_id, policy.max_attempts);
+    None
+}
+
+/// Una conexión concreta: reenvía el backlog, luego audio + ping keepalive por el mismo sink,
+/// mientras una task aparte traduce los mensajes de Deepgram a `TranscriptEvent`.
+async fn run_session(
+    call_id: &str,
+    ws_stream: DeepgramSocket,
+    audio_rx: &mut mpsc::Receiver<Vec<u8>>,
+    transcript_tx: &mpsc::Sender<TranscriptEvent>,
+    backlog: &mut AudioBacklog,
+) -> SessionEnd {
+    let (mut ws_write, mut ws_read) = ws_stream.split();
+
+    // Task para recibir transcripts de Deepgram
+    let call_id_recv = call_id.to_string();
+    let transcript_tx = transcript_tx.clone();
+    let mut receiver = tokio::spawn(async move {
+        while let Some(msg) = ws_read.next().await {
+            match msg {
+                Ok(Message::Text(text)) => {
+                    if let Some(event) = parse_message(&call_id_recv, &text) {
+                        if let Err(e) = transcript_tx.send(event).await {
+                            error!("❌ [CALL:{}] Error enviando transcript: {}", call_id_recv, e);
+                            return SessionEnd::ConsumerGone;
                         }
                     }
                 }
+                Ok(Message::Close(_)) => {
+                    info!("🔚 [CALL:{}] Deepgram cerró conexión", call_id_recv);
+                    return SessionEnd::Disconnected;
+                }
+                Err(e) => {
+                    error!("❌ [CALL:{}] Error en WebSocket Deepgram: {}", call_id_recv, e);
+                    return SessionEnd::Disconnected;
+                }
+                _ => {}
+            }
+        }
+        SessionEnd::Disconnected
+    });
+
+    // Audio acumulado durante el corte
+    let pending = backlog.drain();
+    if !pending.is_empty() {
+        let bytes: usize = pending.iter().map(Vec::len).sum();
+        info!(
+            "⏩ [CALL:{}][WS->Deepgram] Reenviando {} ms de audio del corte ({} ms descartados)",
+            call_id, bytes / 8, backlog.dropped / 8
+        );
+        backlog.dropped = 0;
+        let mut pending = pending.into_iter();
+        while let Some(chunk) = pending.next() {
+            if let Err(e) = ws_write.send(Message::Binary(chunk.clone())).await {
+                error!("❌ [CALL:{}] Error reenviando audio a Deepgram: {}", call_id, e);
+                backlog.push(chunk);
+                pending.for_each(|rest| backlog.push(rest));
+                receiver.abort();
+                return SessionEnd::Disconnected;
             }
-            info!("🔚 [CALL:{}][WS->Deepgram] Cierre de envío", call_id_send);
-        });
-
-        // Task para recibir transcripts de Deepgram
-        let call_id_recv = call_id.clone();
-        tokio::spawn(async move {
-            while let Some(msg) = ws_read.next().await {
-                match msg {
-                    Ok(Message::Text(text)) => {
-                        match serde_json::from_str::<serde_json::Value>(&text) {
-                            Ok(json) => {
-                                // Deepgram envía varios tipos de mensajes
-                                if let Some(transcript_obj) = json.get("channel") {
-                                    match serde_json::from_value::<DeepgramTranscript>(json.clone()) {
-                                        Ok(transcript) => {
-                                            if !transcript.channel.alternatives.is_empty() {
-                                                let text = &transcript.channel.alternatives[0].transcript;
-                                                if !text.trim().is_empty() {
-                                                    let wc = text.split_whitespace().count();
-                                                    debug!("📝 [CALL:{}][Deepgram->WS] msg len={} words={} final={} conf={:.2}", call_id_recv, text.len(), wc, transcript.is_final, transcript.channel.alternatives[0].confidence);
-                                                    info!("💬 [CALL:{}][Deepgram] {}: '{}'", call_id_recv, if transcript.is_final {"FINAL"} else {"INTERIM"}, text);
-                                                    
-                                                    if let Err(e) = transcript_tx.send(transcript).await {
-                                                        error!("❌ [CALL:{}] Error enviando transcript: {}", call_id_recv, e);
-                                                        break;
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        Err(e) => {
-                                            warn!("⚠️ [CALL:{}] Error parseando transcript: {}", call_id_recv, e);
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                warn!("⚠️ [CALL:{}] Error parseando JSON de Deepgram: {}", call_id_recv, e);
-                            }
+        }
+    }
+
+    let mut ping_interval = tokio::time::interval(Duration::from_secs(25));
+    loop {
+        tokio::select! {
+            maybe_audio = audio_rx.recv() => {
+                match maybe_audio {
+                    Some(audio_data) => {
+                        if let Err(e) = ws_write.send(Message::Binary(audio_data.clone())).await {
+                            error!("❌ [CALL:{}] Error enviando audio a Deepgram: {}", call_id, e);
+                            backlog.push(audio_data);
+                            receiver.abort();
+                            return SessionEnd::Disconnected;
                         }
                     }
-                    Ok(Message::Close(_)) => {
-                        info!("🔚 [CALL:{}] Deepgram cerró conexión", call_id_recv);
-                        break;
-                    }
-                    Err(e) => {
-                        error!("❌ [CALL:{}] Error en WebSocket Deepgram: {}", call_id_recv, e);
-                        break;
+                    None => {
+                        debug!("📤 [CALL:{}] Canal de audio cerrado", call_id);
+                        // CloseStream hace que Deepgram entregue lo pendiente antes de cerrar;
+                        // luego Close limpio y tiempo para que lleguen los últimos transcripts
+                        let _ = ws_write.send(Message::Text(r#"{"type":"CloseStream"}"#.to_string())).await;
+                        let _ = ws_write.send(Message::Close(None)).await;
+                        info!("🔚 [CALL:{}][WS->Deepgram] Cierre de envío", call_id);
+                        if tokio::time::timeout(Duration::from_secs(3), &mut receiver).await.is_err() {
+                            receiver.abort();
+                        }
+                        return SessionEnd::AudioClosed;
                     }
-                    _ => {}
                 }
             }
-            info!("🔚 [CALL:{}] Cerrando conexión de recepción Deepgram", call_id_recv);
-        });
+            _ = ping_interval.tick() => {
+                if let Err(e) = ws_write.send(Message::Ping(Vec::new())).await {
+                    warn!("⚠️ [CALL:{}] Ping Deepgram falló: {}", call_id, e);
+                    receiver.abort();
+                    return SessionEnd::Disconnected;
+                } else {
+                    debug!("📶 [CALL:{}] Ping Deepgram enviado", call_id);
+                }
+            }
+            finished = &mut receiver => {
+                return finished.unwrap_or(SessionEnd::Disconnected);
+            }
+        }
+    }
+}
 
-        Ok((audio_tx, transcript_rx))
+/// Traduce un mensaje de texto de Deepgram a `TranscriptEvent`.
+/// `None` para mensajes sin interés para el pipeline (Metadata, transcripts vacíos).
+fn parse_message(call_id: &str, text: &str) -> Option<TranscriptEvent> {
+    let json = match serde_json::from_str::<serde_json::Value>(text) {
+        Ok(json) => json,
+        Err(e) => {
+            warn!("⚠️ [CALL:{}] Error parseando JSON de Deepgram: {}", call_id, e);
+            return None;
+        }
+    };
+
+    // Deepgram envía varios tipos de mensajes
+    match json.get("type").and_then(|t| t.as_str()).unwrap_or("Results") {
+        "Results" => {}
+        "SpeechStarted" => {
+            debug!("🗣️ [CALL:{}][Deepgram] SpeechStarted", call_id);
+            return Some(TranscriptEvent::speech_started());
+        }
+        "UtteranceEnd" => {
+            debug!("⏹️ [CALL:{}][Deepgram] UtteranceEnd", call_id);
+            return Some(TranscriptEvent::utterance_end());
+        }
+        "Metadata" => {
+            info!(
+                "ℹ️ [CALL:{}][Deepgram] Metadata request_id={} model={}",
+                call_id,
+                json.get("request_id").and_then(|v| v.as_str()).unwrap_or("n/a"),
+                json.pointer("/model_info").map(|m| m.to_string()).unwrap_or_default()
+            );
+            return None;
+        }
+        other => {
+            debug!("⏭️ [CALL:{}][Deepgram] Mensaje ignorado: {}", call_id, other);
+            return None;
+        }
+    }
+
+    json.get("channel")?;
+    let transcript = match serde_json::from_value::<DeepgramTranscript>(json) {
+        Ok(transcript) => transcript,
+        Err(e) => {
+            warn!("⚠️ [CALL:{}] Error parseando transcript: {}", call_id, e);
+            return None;
+        }
+    };
+
+    let alternative = transcript.channel.alternatives.first()?;
+    let text = &alternative.transcript;
+    if text.trim().is_empty() {
+        return None;
+    }
+
+    let wc = text.split_whitespace().count();
+    debug!("📝 [CALL:{}][Deepgram->WS] msg len={} words={} final={} conf={:.2}", call_id, text.len(), wc, transcript.is_final, alternative.confidence);
+    info!(
+        "💬 [CALL:{}][Deepgram] {}: '{}'",
+        call_id,
+        if transcript.speech_final { "SPEECH_FINAL" } else if transcript.is_final { "FINAL" } else { "INTERIM" },
+        text
+    );
+
+    Some(TranscriptEvent {
+        speech_final: transcript.speech_final,
+        language: alternative.languages.first().cloned(),
+        ..TranscriptEvent::transcript(text.clone(), alternative.confidence, transcript.is_final)
+    })
+}
+
+#[async_trait]
+impl SttProvider for DeepgramWebSocket {
+    fn name(&self) -> &'static str {
+        "deepgram"
+    }
+
+    fn language(&self, overrides: Option<&SttOverrides>) -> Option<String> {
+        Some(self.config.with_overrides(overrides).language)
+    }
+
+    async fn connect(&self, call_id: String, overrides: Option<&SttOverrides>) -> Result<SttStream> {
+        let (audio_tx, events) = DeepgramWebSocket::connect(self, call_id, overrides).await?;
+        Ok(SttStream { audio_tx, events })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backlog_keeps_most_recent_audio() {
+        let mut backlog = AudioBacklog::new(400);
+        for i in 0..5u8 {
+            backlog.push(vec![i; 160]);
+        }
+        let kept = backlog.drain();
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0][0], 3);
+        assert_eq!(kept[1][0], 4);
+        assert_eq!(backlog.dropped, 480);
+        assert_eq!(backlog.bytes, 0);
+    }
+
+    #[test]
+    fn listen_url_encodes_overrides() {
+        let base = DeepgramConfig {
+            language: "es".to_string(),
+            model: "nova-2".to_string(),
+            endpointing_ms: 200,
+            utterance_end_ms: 500,
+            smart_format: false,
+            punctuate: false,
+            numerals: false,
+            keywords: vec!["Wanda:2".to_string()],
+            keyterms: vec![],
+        };
+        let overrides = SttOverrides {
+            endpointing_ms: Some(350),
+            keywords: vec!["Macarena:2".to_string(), "triple felina".to_string()],
+            keyterms: vec!["pastor alemán & co".to_string()],
+            smart_format: Some(true),
+            ..Default::default()
+        };
+
+        let url = base.with_overrides(Some(&overrides)).listen_url().unwrap();
+        let pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        let get_all = |name: &str| -> Vec<&str> {
+            pairs.iter().filter(|(k, _)| k == name).map(|(_, v)| v.as_str()).collect()
+        };
+
+        assert_eq!(get_all("endpointing"), ["350"]);
+        assert_eq!(get_all("language"), ["es"]);
+        assert_eq!(get_all("smart_format"), ["true"]);
+        assert_eq!(get_all("keywords"), ["Wanda:2", "Macarena:2", "triple felina"]);
+        assert_eq!(get_all("keyterm"), ["pastor alemán & co"]);
+        // Sin vad_events Deepgram nunca manda SpeechStarted (barge-in y turnos)
+        assert_eq!(get_all("vad_events"), ["true"]);
+        // El `&` del término no rompe la query
+        assert!(url.as_str().contains("keyterm=pastor+alem%C3%A1n+%26+co"));
+    }
+
+    #[test]
+    fn parses_turn_events() {
+        let started = parse_message("t", r#"{"type":"SpeechStarted","channel":[0],"timestamp":1.2}"#).unwrap();
+        assert!(started.speech_started && !started.has_text());
+
+        let end = parse_message("t", r#"{"type":"UtteranceEnd","channel":[0,1],"last_word_end":2.1}"#).unwrap();
+        assert!(end.utterance_end);
+
+        assert!(parse_message("t", r#"{"type":"Metadata","request_id":"abc"}"#).is_none());
+
+        let results = parse_message(
+            "t",
+            r#"{"type":"Results","is_final":true,"speech_final":true,"channel":{"alternatives":[{"transcript":"hola","confidence":0.9}]}}"#,
+        ).unwrap();
+        assert_eq!(results.text, "hola");
+        assert!(results.is_final && results.speech_final);
+        assert_eq!(results.language, None);
+
+        let multi = parse_message(
+            "t",
+            r#"{"type":"Results","is_final":true,"channel":{"alternatives":[{"transcript":"hello there","confidence":0.9,"languages":["en","es"]}]}}"#,
+        ).unwrap();
+        assert_eq!(multi.language.as_deref(), Some("en"));
     }
 }
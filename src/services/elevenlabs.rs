@@ -1,16 +1,26 @@
 use reqwest::Client;
 use serde::Serialize;
 use tracing::{info, error};
-use base64::Engine;
+use crate::models::{Language, VoiceProfile};
 
 #[derive(Clone)]
 pub struct ElevenLabsService {
     api_key: String,
-    voice_id: String,
+    default_voice: ResolvedVoice,
+    /// Voz para llamadas en inglés (`ELEVENLABS_VOICE_ID_EN`); sin ella se usa la global
+    english_voice_id: Option<String>,
     base_url: String,
     client: Client,
 }
 
+/// Voz efectiva para una petición TTS (perfil por llamada + valores globales)
+#[derive(Debug, Clone, PartialEq)]
+struct ResolvedVoice {
+    voice_id: String,
+    model_id: String,
+    settings: VoiceSettings,
+}
+
 
 #[derive(Debug, Serialize)]
 struct TextToSpeechRequest {
@@ -19,7 +29,7 @@ struct TextToSpeechRequest {
     voice_settings: VoiceSettings,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 struct VoiceSettings {
     stability: f32,
     similarity_boost: f32,
@@ -37,36 +47,109 @@ impl ElevenLabsService {
         let voice_id = std::env::var("ELEVENLABS_VOICE_ID")
             .unwrap_or_else(|_| "21m00Tcm4TlvDq8ikWAM".to_string());
 
-        info!("✅ ElevenLabs Service inicializado con voice_id: {}", voice_id);
+        // Optimizado para velocidad: modelo turbo es 2-3x más rápido
+        let model_id = std::env::var("ELEVENLABS_MODEL_ID")
+            .unwrap_or_else(|_| "eleven_turbo_v2_5".to_string());
+
+        let english_voice_id = std::env::var("ELEVENLABS_VOICE_ID_EN").ok().filter(|v| !v.is_empty());
+
+        info!("✅ ElevenLabs Service inicializado con voice_id: {}, modelo: {}", voice_id, model_id);
 
         Self {
             api_key,
-            voice_id,
+            default_voice: ResolvedVoice {
+                voice_id,
+                model_id,
+                settings: VoiceSettings {
+                    stability: 0.15,
+                    similarity_boost: 0.85,
+                    style: 0.10,
+                    use_speaker_boost: true,
+                },
+            },
+            english_voice_id,
             base_url: "https://api.elevenlabs.io/v1".to_string(),
             client: Client::new(),
         }
     }
 
-    /// Genera audio desde texto usando ElevenLabs
-    /// Retorna los bytes del audio en formato MP3
-    pub async fn text_to_speech(&self, text: &str) -> anyhow::Result<Vec<u8>> {
-        info!("🎤 Generando audio con ElevenLabs: '{}'", text);
+    /// Perfil de voz para continuar la llamada en `language`. Una voz elegida
+    /// explícitamente por la llamada se respeta.
+    pub fn voice_for_language(&self, profile: Option<&VoiceProfile>, language: Language) -> Option<VoiceProfile> {
+        let english_voice = match (&self.english_voice_id, language) {
+            (Some(voice_id), Language::En) => voice_id,
+            _ => return profile.cloned(),
+        };
+        if profile.is_some_and(|p| p.voice_id.is_some()) {
+            return profile.cloned();
+        }
+
+        Some(VoiceProfile {
+            voice_id: Some(english_voice.clone()),
+            ..profile.cloned().unwrap_or_default()
+        })
+    }
+
+    fn resolve(&self, profile: Option<&VoiceProfile>) -> ResolvedVoice {
+        let defaults = &self.default_voice;
+        let Some(profile) = profile else {
+            return defaults.clone();
+        };
+
+        ResolvedVoice {
+            voice_id: profile.voice_id.clone().unwrap_or_else(|| defaults.voice_id.clone()),
+            model_id: profile.model_id.clone().unwrap_or_else(|| defaults.model_id.clone()),
+            settings: VoiceSettings {
+                stability: profile.stability.unwrap_or(defaults.settings.stability),
+                similarity_boost: profile.similarity_boost.unwrap_or(defaults.settings.similarity_boost),
+                style: profile.style.unwrap_or(defaults.settings.style),
+                use_speaker_boost: profile.use_speaker_boost.unwrap_or(defaults.settings.use_speaker_boost),
+            },
+        }
+    }
+
+    /// Sufijo para claves de caché en S3: vacío con la voz global, hash estable del perfil si no.
+    /// Así un saludo generado con otra voz no pisa ni reutiliza el audio de la voz por defecto.
+    pub fn cache_suffix(&self, profile: Option<&VoiceProfile>) -> String {
+        let voice = self.resolve(profile);
+        if voice == self.default_voice {
+            return String::new();
+        }
+
+        let fingerprint = format!(
+            "{}|{}|{:.2}|{:.2}|{:.2}|{}",
+            voice.voice_id,
+            voice.model_id,
+            voice.settings.stability,
+            voice.settings.similarity_boost,
+            voice.settings.style,
+            voice.settings.use_speaker_boost
+        );
+        // FNV-1a: estable entre versiones de Rust (DefaultHasher no lo garantiza)
+        let hash = fingerprint.bytes().fold(0xcbf29ce484222325u64, |acc, b| {
+            (acc ^ b as u64).wrapping_mul(0x100000001b3)
+        });
+        format!("_v{:016x}", hash)
+    }
+
+    /// Genera audio usando el perfil de voz de la llamada (o el global si es `None`)
+    pub async fn text_to_speech_with_voice(
+        &self,
+        text: &str,
+        profile: Option<&VoiceProfile>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let voice = self.resolve(profile);
+        info!("🎤 Generando audio con ElevenLabs (voice_id: {}, modelo: {}): '{}'", voice.voice_id, voice.model_id, text);
 
-        // Optimizado para velocidad: modelo turbo es 2-3x más rápido
         let request = TextToSpeechRequest {
             text: text.to_string(),
-            model_id: "eleven_turbo_v2_5".to_string(),
-            voice_settings: VoiceSettings {
-                stability: 0.15,
-                similarity_boost: 0.85,
-                style: 0.10,
-                use_speaker_boost: true,
-            },
+            model_id: voice.model_id,
+            voice_settings: voice.settings,
         };
 
         let url = format!(
             "{}/text-to-speech/{}",
-            self.base_url, self.voice_id
+            self.base_url, voice.voice_id
         );
 
         let response = self.client
@@ -88,11 +171,61 @@ impl ElevenLabsService {
 
         Ok(audio_bytes)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> ElevenLabsService {
+        ElevenLabsService {
+            api_key: "test".into(),
+            default_voice: ResolvedVoice {
+                voice_id: "default_voice".into(),
+                model_id: "eleven_turbo_v2_5".into(),
+                settings: VoiceSettings {
+                    stability: 0.15,
+                    similarity_boost: 0.85,
+                    style: 0.10,
+                    use_speaker_boost: true,
+                },
+            },
+            english_voice_id: None,
+            base_url: "http://localhost".into(),
+            client: Client::new(),
+        }
+    }
+
+    #[test]
+    fn default_profile_has_no_cache_suffix() {
+        let service = service();
+        assert_eq!(service.cache_suffix(None), "");
+        assert_eq!(service.cache_suffix(Some(&VoiceProfile::default())), "");
+        // Un override igual al valor global resuelve a la misma voz
+        let same = VoiceProfile { stability: Some(0.15), ..Default::default() };
+        assert_eq!(service.cache_suffix(Some(&same)), "");
+    }
+
+    #[test]
+    fn overriding_a_field_changes_the_suffix() {
+        let service = service();
+        let voice = VoiceProfile { voice_id: Some("otra".into()), ..Default::default() };
+        let stability = VoiceProfile { stability: Some(0.5), ..Default::default() };
+
+        let voice_suffix = service.cache_suffix(Some(&voice));
+        let stability_suffix = service.cache_suffix(Some(&stability));
+        assert!(voice_suffix.starts_with("_v") && voice_suffix.len() == 18);
+        assert_ne!(voice_suffix, stability_suffix);
+        assert_eq!(service.resolve(Some(&stability)).settings.stability, 0.5);
+        assert_eq!(service.resolve(Some(&stability)).voice_id, "default_voice");
+    }
 
-    /// Genera audio y retorna en base64 (útil para debugging o APIs)
-    pub async fn text_to_speech_base64(&self, text: &str) -> anyhow::Result<String> {
-        let audio_bytes = self.text_to_speech(text).await?;
-        let base64_audio = base64::engine::general_purpose::STANDARD.encode(&audio_bytes);
-        Ok(base64_audio)
+    #[test]
+    fn cache_suffix_is_stable() {
+        let profile = VoiceProfile { voice_id: Some("otra".into()), style: Some(0.3), ..Default::default() };
+        let suffix = service().cache_suffix(Some(&profile));
+        assert_eq!(suffix, service().cache_suffix(Some(&profile.clone())));
+        // Fijado: cambiar el fingerprint o el hash invalida los audios ya cacheados en S3
+        assert_eq!(suffix, "_vfa2c04d3b4bbf982");
     }
 }
@@ -0,0 +1,61 @@
+use tokio::sync::broadcast;
+use crate::models::{CallEvent, CallEventKind};
+
+/// Eventos que puede acumular un suscriptor lento antes de perderse los más viejos
+const BUS_CAPACITY: usize = 1024;
+
+/// Bus interno de eventos de llamada de esta réplica: webhooks, media streams y la cola
+/// publican; `/api/events` reparte a cada suscriptor
+pub struct EventBus {
+    tx: broadcast::Sender<CallEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(BUS_CAPACITY);
+        Self { tx }
+    }
+
+    /// Sin suscriptores el evento simplemente se descarta
+    pub fn publish(&self, call_id: &str, kind: CallEventKind) {
+        let _ = self.tx.send(CallEvent {
+            call_control_id: call_id.to_string(),
+            timestamp: chrono::Utc::now(),
+            kind,
+        });
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<CallEvent> {
+        self.tx.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CallState;
+
+    #[tokio::test]
+    async fn subscribers_receive_events_in_order() {
+        let bus = EventBus::new();
+        bus.publish("antes", CallEventKind::AiPaused { paused: true });
+
+        let mut rx = bus.subscribe();
+        bus.publish("c1", CallEventKind::StateChanged { from: CallState::Listening, to: CallState::Thinking });
+        bus.publish("c1", CallEventKind::Ended { reason: "hangup".into() });
+
+        let first = rx.recv().await.unwrap();
+        assert_eq!(first.call_control_id, "c1");
+        assert_eq!(first.kind.name(), "state_changed");
+        let json = serde_json::to_value(&first).unwrap();
+        assert_eq!(json["type"], "state_changed");
+        assert_eq!(json["to"], "thinking");
+        assert_eq!(rx.recv().await.unwrap().kind.name(), "ended");
+    }
+}
@@ -0,0 +1,185 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{info, error, debug};
+use crate::models::{Language, Speaker, TranscriptSource, VoiceProfile};
+use super::{app_state, language};
+use super::AppState;
+
+/// Claves de `AppState::get_or_generate_quick_reply` que se rotan como relleno
+pub const FILLER_KEYS: [&str; 4] = ["filler_1", "filler_2", "filler_3", "filler_4"];
+
+static NEXT_FILLER: AtomicUsize = AtomicUsize::new(0);
+
+/// `QUICK_REPLY_ENABLED` activa el relleno; ya no suena siempre, solo si la respuesta tarda
+pub fn is_enabled() -> bool {
+    std::env::var("QUICK_REPLY_ENABLED")
+        .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+        .unwrap_or(false)
+}
+
+/// Tiempo que se espera por LLM + TTS antes de reproducir un relleno
+pub fn threshold() -> Duration {
+    let ms = std::env::var("FILLER_THRESHOLD_MS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(1200);
+    Duration::from_millis(ms)
+}
+
+/// Relleno programado para un turno. Si la respuesta real está lista antes del umbral,
+/// `cancel()` (o soltar el handle) lo descarta sin que suene nada.
+pub struct FillerHandle {
+    task: Option<JoinHandle<()>>,
+    played: Arc<AtomicBool>,
+}
+
+impl FillerHandle {
+    fn disabled() -> Self {
+        Self { task: None, played: Arc::new(AtomicBool::new(false)) }
+    }
+
+    /// Cancela el relleno pendiente. Devuelve `true` si alcanzó a sonar.
+    pub fn cancel(mut self) -> bool {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        self.played.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for FillerHandle {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+/// Arma el temporizador de relleno para un turno de `call_id`
+pub fn arm(state: Arc<AppState>, call_id: String, voz: Option<VoiceProfile>, language: Language) -> FillerHandle {
+    if !is_enabled() {
+        return FillerHandle::disabled();
+    }
+
+    let delay = threshold();
+    schedule(delay, move |played| async move {
+        let key = next_key();
+        debug!("⏱️ [CALL:{}] Respuesta tarda más de {}ms, usando relleno {}", call_id, delay.as_millis(), key);
+
+        if let Some(url) = state.get_or_generate_quick_reply(key, voz.as_ref(), language).await {
+            if let Err(e) = state.telnyx_service.play_audio(&call_id, &url).await {
+                error!("❌ [CALL:{}] Error reproduciendo relleno: {}", call_id, e);
+            } else {
+                played.store(true, Ordering::SeqCst);
+                info!("🫧 [CALL:{}] Relleno reproducido: {}", call_id, key);
+                if let Some(text) = app_state::quick_reply_text(key, language) {
+                    state.record_transcript(&call_id, Speaker::Bot, TranscriptSource::Filler, text, None).await;
+                }
+            }
+        }
+    })
+}
+
+/// Ejecuta `play` tras `delay` salvo que antes se cancele el handle; `play` marca
+/// el flag solo si Telnyx aceptó reproducir el relleno
+fn schedule<F, Fut>(delay: Duration, play: F) -> FillerHandle
+where
+    F: FnOnce(Arc<AtomicBool>) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let played = Arc::new(AtomicBool::new(false));
+    let play = play(played.clone());
+    // El umbral cuenta desde que se arma, no desde que la tarea llega a ejecutarse
+    let deadline = tokio::time::Instant::now() + delay;
+    let task = tokio::spawn(async move {
+        tokio::time::sleep_until(deadline).await;
+        play.await;
+    });
+    FillerHandle { task: Some(task), played }
+}
+
+/// Rota los rellenos entre turnos (y llamadas) para no repetir siempre el mismo
+fn next_key() -> &'static str {
+    FILLER_KEYS[NEXT_FILLER.fetch_add(1, Ordering::Relaxed) % FILLER_KEYS.len()]
+}
+
+/// Genera (si hace falta) los audios de relleno al arrancar para que el primer uso sea instantáneo
+pub async fn warm_up(state: Arc<AppState>) {
+    if !is_enabled() {
+        return;
+    }
+    let languages: &[Language] = if language::detection_enabled() {
+        &[Language::Es, Language::En]
+    } else {
+        &[Language::Es]
+    };
+    for &lang in languages {
+        for key in FILLER_KEYS {
+            if state.get_or_generate_quick_reply(key, None, lang).await.is_none() {
+                error!("⚠️ No se pudo precargar relleno: {} ({})", key, lang.code());
+            }
+        }
+    }
+    info!("✅ Rellenos precargados ({})", FILLER_KEYS.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELAY: Duration = Duration::from_millis(30);
+
+    fn scheduled() -> (FillerHandle, Arc<AtomicBool>) {
+        let ran = Arc::new(AtomicBool::new(false));
+        let ran_flag = ran.clone();
+        let handle = schedule(DELAY, move |played| async move {
+            ran_flag.store(true, Ordering::SeqCst);
+            played.store(true, Ordering::SeqCst);
+        });
+        (handle, ran)
+    }
+
+    /// Deja correr la tarea del relleno tras mover el reloj
+    async fn advance(by: Duration) {
+        tokio::time::advance(by).await;
+        tokio::task::yield_now().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fast_answer_cancels_filler_before_threshold() {
+        let (handle, ran) = scheduled();
+        advance(DELAY - Duration::from_millis(1)).await;
+        assert!(!ran.load(Ordering::SeqCst));
+
+        assert!(!handle.cancel());
+        advance(DELAY * 2).await;
+        assert!(!ran.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_answer_plays_filler_after_threshold() {
+        let (handle, ran) = scheduled();
+        advance(DELAY).await;
+
+        assert!(ran.load(Ordering::SeqCst));
+        assert!(handle.cancel());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dropping_the_handle_cancels_the_filler() {
+        let (handle, ran) = scheduled();
+        drop(handle);
+        advance(DELAY * 2).await;
+        assert!(!ran.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn rotates_through_every_filler() {
+        let mut keys: Vec<&str> = (0..FILLER_KEYS.len()).map(|_| next_key()).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, FILLER_KEYS);
+    }
+}
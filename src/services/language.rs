@@ -0,0 +1,112 @@
+use tracing::info;
+use crate::models::Language;
+use super::AppState;
+
+const SPANISH_WORDS: &[&str] = &[
+    "el", "la", "los", "las", "de", "del", "que", "y", "en", "un", "una", "es", "por", "para",
+    "con", "hola", "buenos", "buenas", "quiero", "necesito", "gracias", "mi", "tengo", "cita",
+    "perro", "perrito", "gato", "usted", "esta", "está", "qué", "cómo", "sí", "señora", "señor",
+];
+
+const ENGLISH_WORDS: &[&str] = &[
+    "the", "an", "is", "are", "i", "i'm", "you", "my", "to", "and", "of", "it", "hello", "hi",
+    "yes", "need", "want", "would", "like", "appointment", "dog", "cat", "thanks", "thank",
+    "please", "can", "do", "what", "how", "this", "that", "have", "with", "for", "speak", "english",
+];
+
+/// `LANGUAGE_DETECTION_ENABLED`: detectar el idioma en el primer enunciado del cliente
+pub fn detection_enabled() -> bool {
+    std::env::var("LANGUAGE_DETECTION_ENABLED")
+        .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+        .unwrap_or(true)
+}
+
+/// Detección léxica para cuando el proveedor STT no informa idioma.
+/// Devuelve `None` si el texto es corto o ambiguo.
+pub fn detect(text: &str) -> Option<Language> {
+    let mut spanish = 0usize;
+    let mut english = 0usize;
+
+    if text.chars().any(|c| matches!(c, 'ñ' | 'á' | 'é' | 'í' | 'ó' | 'ú' | '¿' | '¡')) {
+        spanish += 2;
+    }
+    for word in text.split(|c: char| !c.is_alphanumeric() && c != '\'') {
+        let word = word.to_lowercase();
+        if SPANISH_WORDS.contains(&word.as_str()) {
+            spanish += 1;
+        }
+        if ENGLISH_WORDS.contains(&word.as_str()) {
+            english += 1;
+        }
+    }
+
+    if english >= 2 && english >= spanish * 2 {
+        Some(Language::En)
+    } else if spanish >= 2 && spanish >= english * 2 {
+        Some(Language::Es)
+    } else {
+        None
+    }
+}
+
+/// Fija el idioma de la llamada con su primer turno (una sola vez por llamada).
+/// Devuelve el nuevo idioma si difiere del que la llamada tenía al empezar,
+/// para que el pipeline cambie STT; voz y prompt se ajustan aquí en la sesión.
+pub async fn settle(state: &AppState, call_id: &str, reported: Option<&str>, text: &str) -> Option<Language> {
+    let detected = reported.and_then(Language::from_code).or_else(|| detect(text));
+    let (language, initial) = state.sessions.update(call_id, |session| {
+        if session.language.is_some() {
+            return None;
+        }
+
+        let initial = session.stt.as_ref()
+            .and_then(|stt| stt.language.as_deref())
+            .and_then(Language::from_code)
+            .unwrap_or_default();
+
+        let language = if detection_enabled() {
+            detected.unwrap_or(initial)
+        } else {
+            initial
+        };
+        session.language = Some(language);
+
+        if language != initial {
+            session.voz = state.elevenlabs_service.voice_for_language(session.voz.as_ref(), language);
+            // Un STT multilingüe (Deepgram "multi") sigue tal cual; uno de idioma fijo pasa al nuevo
+            if state.stt_provider.language(session.stt.as_ref()).as_deref() != Some("multi") {
+                let stt = session.stt.get_or_insert_with(Default::default);
+                stt.language = Some(language.code().to_string());
+            }
+        }
+        Some((language, initial))
+    }).await??;
+
+    if language == initial {
+        return None;
+    }
+
+    info!("🌐 [CALL:{}] Idioma detectado: {} (inicial {})", call_id, language.code(), initial.code());
+    Some(language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_english_and_spanish_utterances() {
+        assert_eq!(detect("Hi, I need an appointment for my dog please"), Some(Language::En));
+        assert_eq!(detect("Hola, quiero una cita para mi perrito"), Some(Language::Es));
+        assert_eq!(detect("¿Tienen consulta mañana?"), Some(Language::Es));
+        assert_eq!(detect("ok"), None);
+        assert_eq!(detect("Rocky"), None);
+    }
+
+    #[test]
+    fn language_codes_accept_regions() {
+        assert_eq!(Language::from_code("en-US"), Some(Language::En));
+        assert_eq!(Language::from_code("es-419"), Some(Language::Es));
+        assert_eq!(Language::from_code("multi"), None);
+    }
+}
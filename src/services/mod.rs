@@ -5,6 +5,23 @@ pub mod s3;
 pub mod elevenlabs;
 pub mod app_state;
 pub mod deepgram_ws;
+pub mod audio_janitor;
+pub mod filler;
+pub mod stt;
+pub mod telnyx_stt;
+pub mod replay_stt;
+pub mod turn;
+pub mod language;
+pub mod silence;
+pub mod call_capture;
+pub mod session_store;
+pub mod redis_store;
+pub mod session_reaper;
+pub mod cdr;
+pub mod call_queue;
+pub mod events;
+pub mod shutdown;
+pub mod campaign;
 
 pub use app_state::AppState;
 pub use session::SessionManager;
@@ -13,8 +30,7 @@ pub use deepgram_ws::DeepgramWebSocket;
 pub use claude::ClaudeService;
 pub use s3::S3Service;
 pub use elevenlabs::ElevenLabsService;
-
-use dashmap::DashMap;
-use std::sync::Arc;
-
-pub type Sessions = Arc<DashMap<String, crate::models::SessionInfo>>;
+pub use audio_janitor::AudioJanitor;
+pub use stt::{SttProvider, TranscriptEvent};
+pub use telnyx_stt::TelnyxTranscriptSource;
+pub use session_store::SessionStore;
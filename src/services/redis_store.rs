@@ -0,0 +1,142 @@
+use async_trait::async_trait;
+use redis::AsyncCommands;
+use redis::aio::ConnectionManager;
+use tracing::info;
+use crate::models::SessionInfo;
+use super::session_store::SessionStore;
+
+const KEY_PREFIX: &str = "session:";
+/// Caducidad de una sesión sin escrituras (llamadas colgadas sin webhook de hangup)
+const DEFAULT_TTL_SECS: u64 = 4 * 3600;
+
+/// Escribe solo si la versión guardada coincide con ARGV[1] (0 = no existe)
+const CAS_SCRIPT: &str = r#"
+local current = tonumber(redis.call('HGET', KEYS[1], 'version') or '0')
+if current ~= tonumber(ARGV[1]) then
+  return 0
+end
+redis.call('HSET', KEYS[1], 'version', current + 1, 'data', ARGV[2])
+redis.call('EXPIRE', KEYS[1], ARGV[3])
+return 1
+"#;
+
+/// Sesiones en un servidor compatible con Redis: un hash `session:{call_id}` con
+/// `version` y `data` (JSON), compartido por todas las réplicas del servicio
+pub struct RedisSessionStore {
+    conn: ConnectionManager,
+    ttl_secs: u64,
+    cas: redis::Script,
+}
+
+impl RedisSessionStore {
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let client = redis::Client::open(url)?;
+        let conn = ConnectionManager::new(client).await?;
+        let ttl_secs = std::env::var("SESSION_STORE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_TTL_SECS);
+
+        info!("✅ Conectado a Redis para sesiones (TTL {}s)", ttl_secs);
+        Ok(Self {
+            conn,
+            ttl_secs,
+            cas: redis::Script::new(CAS_SCRIPT),
+        })
+    }
+
+    fn key(call_id: &str) -> String {
+        format!("{}{}", KEY_PREFIX, call_id)
+    }
+
+    async fn keys(&self) -> anyhow::Result<Vec<String>> {
+        let mut conn = self.conn.clone();
+        let mut iter: redis::AsyncIter<String> = conn.scan_match(format!("{}*", KEY_PREFIX)).await?;
+        let mut keys = Vec::new();
+        while let Some(key) = iter.next_item().await {
+            keys.push(key);
+        }
+        Ok(keys)
+    }
+}
+
+#[async_trait]
+impl SessionStore for RedisSessionStore {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn get_versioned(&self, call_id: &str) -> anyhow::Result<Option<(SessionInfo, u64)>> {
+        let mut conn = self.conn.clone();
+        let (version, data): (Option<u64>, Option<String>) = redis::cmd("HMGET")
+            .arg(Self::key(call_id))
+            .arg("version")
+            .arg("data")
+            .query_async(&mut conn)
+            .await?;
+
+        match (version, data) {
+            (Some(version), Some(data)) => Ok(Some((serde_json::from_str(&data)?, version))),
+            _ => Ok(None),
+        }
+    }
+
+    async fn compare_and_swap(&self, session: &SessionInfo, expected: u64) -> anyhow::Result<bool> {
+        let mut conn = self.conn.clone();
+        let written: i32 = self.cas
+            .key(Self::key(&session.call_control_id))
+            .arg(expected)
+            .arg(serde_json::to_string(session)?)
+            .arg(self.ttl_secs)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(written == 1)
+    }
+
+    async fn remove(&self, call_id: &str) -> anyhow::Result<Option<SessionInfo>> {
+        let mut conn = self.conn.clone();
+        let key = Self::key(call_id);
+        // Leer y borrar en una transacción para que solo una réplica se quede con la sesión
+        let (data, _deleted): (Option<String>, i32) = redis::pipe()
+            .atomic()
+            .hget(&key, "data")
+            .del(&key)
+            .query_async(&mut conn)
+            .await?;
+
+        data.map(|data| serde_json::from_str(&data))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    async fn list(&self) -> anyhow::Result<Vec<SessionInfo>> {
+        let mut conn = self.conn.clone();
+        let mut sessions = Vec::new();
+        for key in self.keys().await? {
+            // La sesión pudo borrarse entre el SCAN y la lectura
+            let data: Option<String> = conn.hget(&key, "data").await?;
+            if let Some(data) = data {
+                sessions.push(serde_json::from_str(&data)?);
+            }
+        }
+        Ok(sessions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use super::*;
+    use crate::services::session_store::tests::exercise_store;
+
+    /// Requiere un redis-server local: `REDIS_URL=redis://127.0.0.1:6379 cargo test`
+    #[tokio::test]
+    async fn redis_store_versions_updates() {
+        let Ok(url) = std::env::var("REDIS_URL") else {
+            eprintln!("REDIS_URL no configurado, se omite el test de Redis");
+            return;
+        };
+        let store = RedisSessionStore::connect(&url).await.unwrap();
+        exercise_store(Arc::new(store), &format!("test-{}", uuid::Uuid::new_v4())).await;
+    }
+}
@@ -0,0 +1,178 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tracing::{info, warn, debug};
+use crate::audio::{self, wav};
+use crate::models::SttOverrides;
+use super::DeepgramWebSocket;
+use super::stt::{SttProvider, SttStream, TranscriptEvent};
+
+/// Pausa por defecto entre líneas de un guion en texto plano
+const DEFAULT_SCRIPT_DELAY_MS: u64 = 1500;
+/// Frames de 20 ms a 8 kHz μ-law, igual que Telnyx Media Streams
+const FRAME_BYTES: usize = 160;
+
+/// Línea de un guion JSONL: `{"delay_ms": 800, "text": "hola", "is_final": true, "confidence": 0.95}`
+#[derive(Debug, Deserialize)]
+struct ScriptLine {
+    #[serde(default)]
+    delay_ms: Option<u64>,
+    #[serde(flatten)]
+    event: TranscriptEvent,
+}
+
+pub enum ReplaySource {
+    /// Guion de transcripts (JSONL o texto plano, una frase final por línea)
+    Script(PathBuf),
+    /// Archivo WAV que se envía en tiempo real a otro proveedor en lugar del audio de la llamada
+    Audio { path: PathBuf, inner: Arc<dyn SttProvider> },
+}
+
+/// Proveedor de reproducción para probar el pipeline sin llamadas ni STT reales
+pub struct ReplayProvider {
+    source: ReplaySource,
+}
+
+impl ReplayProvider {
+    pub fn new(source: ReplaySource) -> Self {
+        Self { source }
+    }
+
+    /// `STT_REPLAY_FILE`: `.wav` se transcribe con Deepgram; cualquier otro archivo es un guion
+    pub fn from_env() -> anyhow::Result<Self> {
+        let path = PathBuf::from(
+            std::env::var("STT_REPLAY_FILE")
+                .map_err(|_| anyhow::anyhow!("STT_REPLAY_FILE no configurado"))?,
+        );
+        if !path.exists() {
+            return Err(anyhow::anyhow!("STT_REPLAY_FILE no existe: {}", path.display()));
+        }
+
+        let is_wav = path.extension()
+            .map(|ext| ext.eq_ignore_ascii_case("wav"))
+            .unwrap_or(false);
+        let source = if is_wav {
+            ReplaySource::Audio { path, inner: Arc::new(DeepgramWebSocket::new()) }
+        } else {
+            ReplaySource::Script(path)
+        };
+        Ok(Self::new(source))
+    }
+}
+
+pub fn parse_script(contents: &str) -> Vec<(u64, TranscriptEvent)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| match serde_json::from_str::<ScriptLine>(line) {
+            Ok(parsed) => (parsed.delay_ms.unwrap_or(DEFAULT_SCRIPT_DELAY_MS), parsed.event),
+            Err(_) => (DEFAULT_SCRIPT_DELAY_MS, TranscriptEvent::final_utterance(line, 1.0)),
+        })
+        .collect()
+}
+
+/// WAV (PCM16 o G.711, mono) -> μ-law 8 kHz
+fn load_wav_as_ulaw(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let file = wav::read_wav(bytes)?;
+    let channels = file.spec.channels.max(1) as usize;
+    let mono: Vec<i16> = file.to_pcm16()
+        .chunks(channels)
+        .map(|frame| (frame.iter().map(|&s| s as i32).sum::<i32>() / frame.len() as i32) as i16)
+        .collect();
+    audio::pcm16_to_ulaw(&mono, file.spec.sample_rate)
+}
+
+#[async_trait]
+impl SttProvider for ReplayProvider {
+    fn name(&self) -> &'static str {
+        "replay"
+    }
+
+    /// El guion o audio de replay es siempre la voz del cliente
+    fn supports_outbound(&self) -> bool {
+        false
+    }
+
+    async fn connect(&self, call_id: String, overrides: Option<&SttOverrides>) -> anyhow::Result<SttStream> {
+        let (audio_tx, mut live_audio_rx) = mpsc::channel::<Vec<u8>>(100);
+
+        match &self.source {
+            ReplaySource::Script(path) => {
+                let script = parse_script(&tokio::fs::read_to_string(path).await?);
+                info!("▶️ [CALL:{}][STT] Replay de guion {} ({} eventos)", call_id, path.display(), script.len());
+
+                let (event_tx, events) = mpsc::channel::<TranscriptEvent>(100);
+                tokio::spawn(async move {
+                    for (delay_ms, event) in script {
+                        tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+                        if event_tx.send(event).await.is_err() {
+                            break;
+                        }
+                    }
+                    debug!("🔚 [CALL:{}][STT] Guion terminado", call_id);
+                });
+                // El audio real de la llamada se ignora
+                tokio::spawn(async move { while live_audio_rx.recv().await.is_some() {} });
+
+                Ok(SttStream { audio_tx, events })
+            }
+            ReplaySource::Audio { path, inner } => {
+                let ulaw = load_wav_as_ulaw(&tokio::fs::read(path).await?)?;
+                info!("▶️ [CALL:{}][STT] Replay de audio {} ({} ms) vía {}", call_id, path.display(), ulaw.len() / 8, inner.name());
+
+                let inner_stream = inner.connect(call_id.clone(), overrides).await?;
+                let inner_audio_tx = inner_stream.audio_tx;
+                tokio::spawn(async move {
+                    let mut ticker = tokio::time::interval(tokio::time::Duration::from_millis(20));
+                    for frame in ulaw.chunks(FRAME_BYTES) {
+                        ticker.tick().await;
+                        if inner_audio_tx.send(frame.to_vec()).await.is_err() {
+                            warn!("⚠️ [CALL:{}][STT] Proveedor interno cerró durante el replay", call_id);
+                            return;
+                        }
+                    }
+                    debug!("🔚 [CALL:{}][STT] Audio de replay enviado completo", call_id);
+                    // Mantener abierta la sesión interna hasta que termine la llamada
+                    while live_audio_rx.recv().await.is_some() {}
+                });
+
+                Ok(SttStream { audio_tx, events: inner_stream.events })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_jsonl_and_plain_lines() {
+        let script = parse_script(
+            "# saludo\n{\"delay_ms\": 200, \"speech_started\": true}\nhola, quiero una cita\n{\"text\": \"para mañana\", \"is_final\": true, \"confidence\": 0.8, \"utterance_end\": true}\n",
+        );
+        assert_eq!(script.len(), 3);
+        assert_eq!(script[0].0, 200);
+        assert!(script[0].1.speech_started && !script[0].1.has_text());
+        assert_eq!(script[1].1, TranscriptEvent::final_utterance("hola, quiero una cita", 1.0));
+        assert_eq!(script[2].0, DEFAULT_SCRIPT_DELAY_MS);
+        assert!(script[2].1.utterance_end);
+    }
+
+    #[tokio::test]
+    async fn script_replay_emits_events_in_order() {
+        let path = std::env::temp_dir().join(format!("stt_replay_{}.jsonl", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "{\"delay_ms\": 1, \"text\": \"uno\", \"is_final\": true}\n{\"delay_ms\": 1, \"text\": \"dos\", \"is_final\": true}\n").unwrap();
+
+        let provider = ReplayProvider::new(ReplaySource::Script(path.clone()));
+        let mut stream = provider.connect("test-call".to_string(), None).await.unwrap();
+        assert_eq!(stream.events.recv().await.unwrap().text, "uno");
+        assert_eq!(stream.events.recv().await.unwrap().text, "dos");
+        assert!(stream.events.recv().await.is_none());
+
+        std::fs::remove_file(path).ok();
+    }
+}
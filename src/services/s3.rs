@@ -1,6 +1,14 @@
 use aws_sdk_s3::Client as S3Client;
 use tracing::{info, error};
 use aws_config::BehaviorVersion;
+use chrono::{DateTime, Utc};
+
+/// Objeto listado en el bucket (clave + fecha de última modificación)
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    pub key: String,
+    pub last_modified: Option<DateTime<Utc>>,
+}
 
 #[derive(Clone)]
 pub struct S3Service {
@@ -31,6 +39,15 @@ impl S3Service {
         key: &str,
         data: Vec<u8>,
     ) -> anyhow::Result<String> {
+        self.upload_object(key, data, "audio/mpeg").await
+    }
+
+    pub async fn upload_object(
+        &self,
+        key: &str,
+        data: Vec<u8>,
+        content_type: &str,
+    ) -> anyhow::Result<String> {
 
         // Log upload details
         info!("⬆️ S3 put_object request - bucket: {}, key: {}, size_bytes: {}", self.bucket, key, data.len());
@@ -40,7 +57,7 @@ impl S3Service {
             .bucket(&self.bucket)
             .key(key)
             .body(aws_sdk_s3::primitives::ByteStream::from(data))
-            .content_type("audio/mpeg");
+            .content_type(content_type);
 
         match put_req.send().await {
             Ok(_) => {}
@@ -83,4 +100,52 @@ impl S3Service {
             Err(_) => false,
         }
     }
+
+    /// Lista todos los objetos bajo un prefijo (sigue la paginación de S3)
+    pub async fn list_objects(&self, prefix: &str) -> anyhow::Result<Vec<StoredObject>> {
+        let mut objects = Vec::new();
+        let mut continuation_token: Option<String> = None;
+
+        loop {
+            let response = self.client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(prefix)
+                .set_continuation_token(continuation_token.take())
+                .send()
+                .await
+                .map_err(|e| anyhow::anyhow!("S3 list_objects_v2 failed: {}", e))?;
+
+            for object in response.contents() {
+                if let Some(key) = object.key() {
+                    objects.push(StoredObject {
+                        key: key.to_string(),
+                        last_modified: object.last_modified()
+                            .and_then(|t| DateTime::<Utc>::from_timestamp(t.secs(), 0)),
+                    });
+                }
+            }
+
+            match response.next_continuation_token() {
+                Some(token) if response.is_truncated().unwrap_or(false) => {
+                    continuation_token = Some(token.to_string());
+                }
+                _ => break,
+            }
+        }
+
+        Ok(objects)
+    }
+
+    pub async fn delete_object(&self, key: &str) -> anyhow::Result<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("S3 delete_object failed: {}", e))?;
+
+        Ok(())
+    }
 }
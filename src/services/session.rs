@@ -1,26 +1,104 @@
-use dashmap::DashMap;
-use std::sync::Arc;
 use chrono::Utc;
-use crate::models::SessionInfo;
-
-pub type Sessions = Arc<DashMap<String, SessionInfo>>;
+use crate::models::{CallState, CallMetrics, ClientState, LatencyStage, SessionInfo, Speaker, TranscriptLine, TranscriptSource, UNKNOWN_CALLER_NAME, UNKNOWN_CALLER_PHONE};
 
 pub struct SessionManager;
 
 impl SessionManager {
     pub fn create_session(call_control_id: String, nombre: String, telefono: String) -> SessionInfo {
+        let now = Utc::now();
         SessionInfo {
             call_control_id: call_control_id.clone(),
             nombre,
             telefono,
             contexto: None,
-            created_at: Utc::now(),
+            created_at: now,
             conversation_history: Vec::new(),
             transcription_started: false,
+            voz: None,
+            stt: None,
+            grabacion: None,
+            transcript: Vec::new(),
+            language: None,
+            low_confidence_turns: 0,
+            low_confidence_streak: 0,
+            state: CallState::default(),
+            direction: Default::default(),
+            answered_at: None,
+            metrics: CallMetrics::default(),
+            ai_paused: false,
+            last_activity: now,
+        }
+    }
+
+    pub fn from_client_state(call_control_id: String, client_state: &ClientState) -> SessionInfo {
+        let mut session = Self::create_session(
+            call_control_id,
+            client_state.nombre.clone(),
+            client_state.telefono.clone(),
+        );
+        session.contexto = client_state.contexto.clone();
+        session.voz = client_state.voz.clone();
+        session.stt = client_state.stt.clone();
+        session.grabacion = client_state.grabacion;
+        session
+    }
+
+    /// Completa una sesión existente con lo que sabe el otro punto de entrada
+    /// (webhook o media stream) sin pisar identidad ni estado ya registrados
+    pub fn merge(existing: &mut SessionInfo, incoming: &SessionInfo) {
+        if existing.nombre == UNKNOWN_CALLER_NAME {
+            existing.nombre = incoming.nombre.clone();
+        }
+        if existing.telefono == UNKNOWN_CALLER_PHONE {
+            existing.telefono = incoming.telefono.clone();
+        }
+        if existing.contexto.is_none() {
+            existing.contexto = incoming.contexto.clone();
+        }
+        if existing.voz.is_none() {
+            existing.voz = incoming.voz.clone();
+        }
+        if existing.stt.is_none() {
+            existing.stt = incoming.stt.clone();
+        }
+        if existing.grabacion.is_none() {
+            existing.grabacion = incoming.grabacion;
+        }
+    }
+
+    /// Aplica la transición si es válida: `Ok(true)` si cambió el estado, `Ok(false)`
+    /// si ya estaba en él, `Err(actual)` si no se permite desde el estado actual
+    pub fn transition(session: &mut SessionInfo, next: CallState) -> Result<bool, CallState> {
+        if session.state == next {
+            return Ok(false);
         }
+        if !session.state.can_transition_to(next) {
+            return Err(session.state);
+        }
+        if next == CallState::Answered {
+            session.answered_at = Some(Utc::now());
+        }
+        session.state = next;
+        Ok(true)
+    }
+
+    /// Turno del cliente que llega al LLM
+    pub fn record_turn(session: &mut SessionInfo) {
+        Self::touch(session);
+        session.metrics.turns += 1;
+    }
+
+    pub fn record_latency(session: &mut SessionInfo, stage: LatencyStage, ms: u64) {
+        session.metrics.record_latency(stage, ms);
+    }
+
+    /// Hubo actividad en la llamada (aleja al reaper)
+    pub fn touch(session: &mut SessionInfo) {
+        session.last_activity = Utc::now();
     }
 
     pub fn add_to_history(session: &mut SessionInfo, message: String) {
+        Self::touch(session);
         session.conversation_history.push(message);
         // Mantener solo el último mensaje (contexto muy corto = respuestas rápidas)
         if session.conversation_history.len() > 1 {
@@ -28,6 +106,35 @@ impl SessionManager {
         }
     }
 
+    pub fn add_transcript(
+        session: &mut SessionInfo,
+        speaker: Speaker,
+        source: TranscriptSource,
+        text: &str,
+        confidence: Option<f64>,
+    ) {
+        Self::touch(session);
+        session.transcript.push(TranscriptLine {
+            speaker,
+            source,
+            text: text.to_string(),
+            confidence,
+            timestamp: Utc::now(),
+        });
+    }
+
+    /// Devuelve cuántos turnos ininteligibles lleva seguidos
+    pub fn record_low_confidence(session: &mut SessionInfo) -> u32 {
+        Self::touch(session);
+        session.low_confidence_turns += 1;
+        session.low_confidence_streak += 1;
+        session.low_confidence_streak
+    }
+
+    pub fn clear_low_confidence_streak(session: &mut SessionInfo) {
+        session.low_confidence_streak = 0;
+    }
+
     pub fn get_conversation_context(session: &SessionInfo) -> String {
         session.conversation_history.join(" | ")
     }
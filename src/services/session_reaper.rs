@@ -0,0 +1,193 @@
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use tracing::{info, warn, debug};
+use crate::models::SessionInfo;
+use super::{campaign, AppState};
+
+/// Por qué se cerró la sesión de una llamada
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEndReason {
+    /// Webhook `call.hangup`
+    Hangup,
+    /// El media stream terminó (stop o WebSocket cerrado)
+    StreamStopped,
+    /// Sin actividad durante `SESSION_IDLE_TIMEOUT_SECS`
+    Idle,
+    /// Superó `SESSION_MAX_LIFETIME_SECS`
+    MaxLifetime,
+    /// Seguía viva al vencer el plazo de drenaje del apagado
+    Shutdown,
+}
+
+impl SessionEndReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SessionEndReason::Hangup => "hangup",
+            SessionEndReason::StreamStopped => "stream_stopped",
+            SessionEndReason::Idle => "idle",
+            SessionEndReason::MaxLifetime => "max_lifetime",
+            SessionEndReason::Shutdown => "shutdown",
+        }
+    }
+}
+
+/// Cierre periódico de sesiones huérfanas (hangup perdido, stream sin webhook)
+pub struct SessionReaper {
+    idle_timeout: Duration,
+    max_lifetime: Duration,
+    interval: std::time::Duration,
+    campaign_retention: Duration,
+}
+
+impl SessionReaper {
+    pub fn from_env() -> Self {
+        let idle_secs = std::env::var("SESSION_IDLE_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(600);
+        let max_lifetime_secs = std::env::var("SESSION_MAX_LIFETIME_SECS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(3600);
+        let interval_secs = std::env::var("SESSION_REAPER_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(60)
+            .max(5);
+
+        info!(
+            "🧟 Session reaper: inactividad={}s, vida máxima={}s, intervalo={}s",
+            idle_secs, max_lifetime_secs, interval_secs
+        );
+
+        Self {
+            idle_timeout: Duration::seconds(idle_secs),
+            max_lifetime: Duration::seconds(max_lifetime_secs),
+            interval: std::time::Duration::from_secs(interval_secs),
+            campaign_retention: campaign::retention(),
+        }
+    }
+
+    /// Motivo para cerrar la sesión, o `None` si sigue viva
+    pub fn reap_reason(&self, session: &SessionInfo, now: DateTime<Utc>) -> Option<SessionEndReason> {
+        if now - session.created_at >= self.max_lifetime {
+            Some(SessionEndReason::MaxLifetime)
+        } else if now - session.last_activity >= self.idle_timeout {
+            Some(SessionEndReason::Idle)
+        } else {
+            None
+        }
+    }
+
+    /// Lanza el loop del reaper en segundo plano
+    pub fn spawn(self, state: Arc<AppState>) {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            loop {
+                ticker.tick().await;
+                self.sweep(&state).await;
+            }
+        });
+    }
+
+    /// Devuelve las llamadas cerradas en esta pasada
+    pub async fn sweep(&self, state: &Arc<AppState>) -> Vec<(String, SessionEndReason)> {
+        let now = Utc::now();
+        let mut reaped = Vec::new();
+
+        for session in state.sessions.all().await {
+            let Some(reason) = self.reap_reason(&session, now) else {
+                continue;
+            };
+            let call_id = session.call_control_id;
+            // Otra réplica (o el hangup) pudo cerrarla entre el listado y ahora
+            if state.end_call(&call_id, reason).await.is_none() {
+                continue;
+            }
+
+            warn!(
+                "🧟 [CALL:{}] Sesión huérfana cerrada ({}): creada {}, última actividad {}",
+                call_id, reason.as_str(), session.created_at, session.last_activity
+            );
+            // La llamada puede seguir viva en Telnyx (p. ej. vida máxima); sin sesión ya no se atiende
+            if let Err(e) = state.telnyx_service.hangup(&call_id).await {
+                debug!("☎️ [CALL:{}] Hangup del reaper sin efecto: {}", call_id, e);
+            }
+            reaped.push((call_id, reason));
+        }
+
+        // Streams de esta réplica cuya sesión cerró otra réplica
+        let local_streams: Vec<String> = state.streams.iter().map(|entry| entry.key().clone()).collect();
+        for call_id in local_streams {
+            if session_gone(state, &call_id).await && state.close_stream(&call_id) {
+                warn!("🧟 [CALL:{}] Media stream sin sesión cerrado", call_id);
+            }
+        }
+        // Colas locales de llamadas que cerró otra réplica
+        let local_queues: Vec<String> = state.call_queues.iter().map(|entry| entry.key().clone()).collect();
+        for call_id in local_queues {
+            if session_gone(state, &call_id).await {
+                state.call_queues.remove(&call_id);
+            }
+        }
+
+        // Llamadas de campaña cuyo cierre procesó otra réplica: se devuelve su cupo
+        for call_id in state.campaigns.call_ids() {
+            if session_gone(state, &call_id).await {
+                warn!("🧟 [CALL:{}] Llamada de campaña cerrada fuera de esta réplica", call_id);
+                state.campaigns.call_ended(&call_id, false);
+            }
+        }
+        let evicted = state.campaigns.evict_finished(self.campaign_retention, now);
+        if evicted > 0 {
+            info!("🧹 [Reaper] {} campañas terminadas olvidadas", evicted);
+        }
+
+        if !reaped.is_empty() {
+            info!("🧟 [Reaper] {} sesiones cerradas", reaped.len());
+        }
+        reaped
+    }
+}
+
+/// `true` solo si el almacén confirma que la sesión ya no existe; un error del backend
+/// (p. ej. Redis caído un momento) no cuenta como llamada cerrada
+pub async fn session_gone(state: &AppState, call_id: &str) -> bool {
+    match state.sessions.get_versioned(call_id).await {
+        Ok(found) => found.is_none(),
+        Err(e) => {
+            warn!("⚠️ [CALL:{}] No se pudo consultar la sesión en el reaper: {}", call_id, e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::SessionManager;
+
+    fn reaper() -> SessionReaper {
+        SessionReaper {
+            idle_timeout: Duration::seconds(600),
+            max_lifetime: Duration::seconds(3600),
+            interval: std::time::Duration::from_secs(60),
+            campaign_retention: Duration::hours(24),
+        }
+    }
+
+    #[test]
+    fn reaps_idle_and_expired_sessions() {
+        let reaper = reaper();
+        let mut session = SessionManager::create_session("c".into(), "x".into(), "y".into());
+        let start = session.created_at;
+
+        assert_eq!(reaper.reap_reason(&session, start + Duration::seconds(599)), None);
+        assert_eq!(reaper.reap_reason(&session, start + Duration::seconds(600)), Some(SessionEndReason::Idle));
+
+        // La actividad mantiene viva la sesión, pero no más allá de la vida máxima
+        session.last_activity = start + Duration::seconds(3000);
+        assert_eq!(reaper.reap_reason(&session, start + Duration::seconds(3500)), None);
+        assert_eq!(reaper.reap_reason(&session, start + Duration::seconds(3600)), Some(SessionEndReason::MaxLifetime));
+    }
+}
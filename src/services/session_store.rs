@@ -0,0 +1,294 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use tracing::{info, error, warn};
+use crate::models::SessionInfo;
+use super::redis_store::RedisSessionStore;
+use super::SessionManager;
+
+/// Reintentos de una actualización optimista antes de rendirse
+const MAX_UPDATE_ATTEMPTS: usize = 10;
+
+/// Almacén de sesiones de llamada. Cada sesión lleva una versión que sube con cada
+/// escritura; `compare_and_swap` solo escribe si nadie la cambió desde que se leyó,
+/// así varias réplicas pueden modificar la misma llamada sin pisarse.
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Sesión y su versión actual
+    async fn get_versioned(&self, call_id: &str) -> anyhow::Result<Option<(SessionInfo, u64)>>;
+
+    /// Escribe si la versión guardada sigue siendo `expected` (0 = la sesión no existe)
+    async fn compare_and_swap(&self, session: &SessionInfo, expected: u64) -> anyhow::Result<bool>;
+
+    async fn remove(&self, call_id: &str) -> anyhow::Result<Option<SessionInfo>>;
+
+    async fn list(&self) -> anyhow::Result<Vec<SessionInfo>>;
+}
+
+/// Atajos para los handlers: registran los errores del backend y devuelven `Option`
+impl dyn SessionStore {
+    pub async fn get(&self, call_id: &str) -> Option<SessionInfo> {
+        match self.get_versioned(call_id).await {
+            Ok(found) => found.map(|(session, _)| session),
+            Err(e) => {
+                error!("❌ [CALL:{}][Sessions] Error leyendo sesión ({}): {}", call_id, self.name(), e);
+                None
+            }
+        }
+    }
+
+    pub async fn take(&self, call_id: &str) -> Option<SessionInfo> {
+        match self.remove(call_id).await {
+            Ok(removed) => removed,
+            Err(e) => {
+                error!("❌ [CALL:{}][Sessions] Error borrando sesión ({}): {}", call_id, self.name(), e);
+                None
+            }
+        }
+    }
+
+    /// Punto de entrada único de una llamada (webhook `call.answered` o `start` del media stream):
+    /// crea la sesión si no existe; si ya la abrió el otro, la completa y devuelve ese registro
+    pub async fn open(&self, incoming: SessionInfo) -> SessionInfo {
+        let call_id = incoming.call_control_id.clone();
+        for _ in 0..MAX_UPDATE_ATTEMPTS {
+            let result = match self.get_versioned(&call_id).await {
+                Ok(None) => self.compare_and_swap(&incoming, 0).await.map(|created| {
+                    created.then(|| {
+                        info!("🆕 [CALL:{}][Sessions] Sesión creada para {} ({})", call_id, incoming.nombre, incoming.telefono);
+                        incoming.clone()
+                    })
+                }),
+                Ok(Some((mut existing, version))) => {
+                    SessionManager::merge(&mut existing, &incoming);
+                    self.compare_and_swap(&existing, version).await.map(|merged| {
+                        merged.then(|| {
+                            info!("🔗 [CALL:{}][Sessions] Sesión existente reutilizada: {} ({})", call_id, existing.nombre, existing.telefono);
+                            existing
+                        })
+                    })
+                }
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(Some(session)) => return session,
+                Ok(None) => continue,
+                Err(e) => {
+                    error!("❌ [CALL:{}][Sessions] Error abriendo sesión ({}): {}", call_id, self.name(), e);
+                    return incoming;
+                }
+            }
+        }
+
+        warn!("⚠️ [CALL:{}][Sessions] Apertura abandonada tras {} conflictos", call_id, MAX_UPDATE_ATTEMPTS);
+        incoming
+    }
+
+    pub async fn all(&self) -> Vec<SessionInfo> {
+        self.list().await.unwrap_or_else(|e| {
+            error!("❌ [Sessions] Error listando sesiones ({}): {}", self.name(), e);
+            Vec::new()
+        })
+    }
+
+    /// Lee-modifica-escribe con reintentos. `None` si la sesión no existe o el backend falla.
+    pub async fn update<R, F>(&self, call_id: &str, mut apply: F) -> Option<R>
+    where
+        F: FnMut(&mut SessionInfo) -> R + Send,
+        R: Send,
+    {
+        for _ in 0..MAX_UPDATE_ATTEMPTS {
+            let (mut session, version) = match self.get_versioned(call_id).await {
+                Ok(Some(found)) => found,
+                Ok(None) => return None,
+                Err(e) => {
+                    error!("❌ [CALL:{}][Sessions] Error leyendo sesión ({}): {}", call_id, self.name(), e);
+                    return None;
+                }
+            };
+
+            let result = apply(&mut session);
+            match self.compare_and_swap(&session, version).await {
+                Ok(true) => return Some(result),
+                Ok(false) => continue,
+                Err(e) => {
+                    error!("❌ [CALL:{}][Sessions] Error guardando sesión ({}): {}", call_id, self.name(), e);
+                    return None;
+                }
+            }
+        }
+
+        warn!("⚠️ [CALL:{}][Sessions] Actualización abandonada tras {} conflictos", call_id, MAX_UPDATE_ATTEMPTS);
+        None
+    }
+}
+
+/// Backend en memoria del proceso (una sola réplica)
+#[derive(Default)]
+pub struct MemorySessionStore {
+    sessions: DashMap<String, (u64, SessionInfo)>,
+}
+
+impl MemorySessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SessionStore for MemorySessionStore {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn get_versioned(&self, call_id: &str) -> anyhow::Result<Option<(SessionInfo, u64)>> {
+        Ok(self.sessions.get(call_id).map(|entry| (entry.1.clone(), entry.0)))
+    }
+
+    async fn compare_and_swap(&self, session: &SessionInfo, expected: u64) -> anyhow::Result<bool> {
+        match self.sessions.entry(session.call_control_id.clone()) {
+            Entry::Occupied(mut entry) if entry.get().0 == expected => {
+                entry.insert((expected + 1, session.clone()));
+                Ok(true)
+            }
+            Entry::Vacant(entry) if expected == 0 => {
+                entry.insert((1, session.clone()));
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn remove(&self, call_id: &str) -> anyhow::Result<Option<SessionInfo>> {
+        Ok(self.sessions.remove(call_id).map(|(_, (_, session))| session))
+    }
+
+    async fn list(&self) -> anyhow::Result<Vec<SessionInfo>> {
+        Ok(self.sessions.iter().map(|entry| entry.1.clone()).collect())
+    }
+}
+
+/// `SESSION_STORE=memory` (por defecto) o `redis` (usa `REDIS_URL`)
+pub async fn store_from_env() -> anyhow::Result<Arc<dyn SessionStore>> {
+    let backend = std::env::var("SESSION_STORE").unwrap_or_else(|_| "memory".to_string());
+    let store: Arc<dyn SessionStore> = match backend.to_lowercase().as_str() {
+        "redis" => {
+            let url = std::env::var("REDIS_URL")
+                .map_err(|_| anyhow::anyhow!("SESSION_STORE=redis requiere REDIS_URL"))?;
+            Arc::new(RedisSessionStore::connect(&url).await?)
+        }
+        "memory" => Arc::new(MemorySessionStore::new()),
+        other => {
+            warn!("⚠️ SESSION_STORE desconocido '{}', usando memoria", other);
+            Arc::new(MemorySessionStore::new())
+        }
+    };
+    info!("✅ Sesiones en backend: {}", store.name());
+    Ok(store)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::services::SessionManager;
+
+    /// Contrato común; el backend Redis lo ejecuta en sus propios tests
+    pub(crate) async fn exercise_store(store: Arc<dyn SessionStore>, call_id: &str) {
+        store.remove(call_id).await.unwrap();
+        let session = SessionManager::create_session(call_id.to_string(), "Ana".to_string(), "+57300".to_string());
+
+        assert!(store.compare_and_swap(&session, 0).await.unwrap());
+        assert!(!store.compare_and_swap(&session, 0).await.unwrap(), "crear dos veces debe fallar");
+
+        let (stored, version) = store.get_versioned(call_id).await.unwrap().unwrap();
+        assert_eq!(stored.nombre, "Ana");
+
+        // Otra réplica escribe entre medias: la versión leída queda obsoleta
+        store.update(call_id, |s| s.nombre = "Ana María".to_string()).await.unwrap();
+        assert!(!store.compare_and_swap(&stored, version).await.unwrap());
+        assert_eq!(store.get(call_id).await.unwrap().nombre, "Ana María");
+
+        assert!(store.list().await.unwrap().iter().any(|s| s.call_control_id == call_id));
+        assert!(store.remove(call_id).await.unwrap().is_some());
+        assert!(store.get(call_id).await.is_none());
+        assert_eq!(store.update(call_id, |_| ()).await, None);
+    }
+
+    #[tokio::test]
+    async fn memory_store_versions_updates() {
+        exercise_store(Arc::new(MemorySessionStore::new()), "mem-call").await;
+    }
+
+    #[tokio::test]
+    async fn open_merges_both_entry_points() {
+        use crate::models::ClientState;
+
+        let store: Arc<dyn SessionStore> = Arc::new(MemorySessionStore::new());
+        // El media stream llega primero sin client_state
+        let from_stream = SessionManager::from_client_state("c".into(), &ClientState::unknown("c"));
+        store.open(from_stream).await;
+        store.update("c", |s| s.conversation_history.push("hola".into())).await;
+
+        // El webhook trae la identidad real: completa, no reemplaza
+        let mut client_state = ClientState::unknown("c");
+        client_state.nombre = "Ana".into();
+        client_state.telefono = "+57300".into();
+        client_state.contexto = Some("Vacuna de Rocky".into());
+        let session = store.open(SessionManager::from_client_state("c".into(), &client_state)).await;
+        assert_eq!(session.nombre, "Ana");
+        assert_eq!(session.contexto.as_deref(), Some("Vacuna de Rocky"));
+        assert_eq!(session.conversation_history, vec!["hola".to_string()]);
+
+        // Un stream posterior sin datos no borra la identidad
+        let again = store.open(SessionManager::from_client_state("c".into(), &ClientState::unknown("c"))).await;
+        assert_eq!(again.nombre, "Ana");
+        assert_eq!(again.telefono, "+57300");
+    }
+
+    #[tokio::test]
+    async fn update_retries_after_an_interleaved_write() {
+        let memory = Arc::new(MemorySessionStore::new());
+        let store: Arc<dyn SessionStore> = memory.clone();
+        store.open(SessionManager::create_session("c".into(), "x".into(), "y".into())).await;
+
+        // Otro escritor gana la carrera entre la lectura y el CAS del primer intento
+        let mut attempts = 0;
+        let updated = store.update("c", |s| {
+            attempts += 1;
+            if attempts == 1 {
+                let mut entry = memory.sessions.get_mut("c").unwrap();
+                entry.0 += 1;
+                entry.1.conversation_history.push("intruso".into());
+            }
+            s.conversation_history.push("propio".into());
+        }).await;
+
+        assert_eq!(updated, Some(()));
+        assert_eq!(attempts, 2);
+        assert_eq!(store.get("c").await.unwrap().conversation_history, ["intruso", "propio"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_updates_are_not_lost() {
+        let store: Arc<dyn SessionStore> = Arc::new(MemorySessionStore::new());
+        store.open(SessionManager::create_session("c".into(), "x".into(), "y".into())).await;
+
+        let tasks: Vec<_> = (0..20)
+            .map(|i| {
+                let store = store.clone();
+                tokio::spawn(async move {
+                    store.update("c", |s| s.conversation_history.push(i.to_string())).await
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        assert_eq!(store.get("c").await.unwrap().conversation_history.len(), 20);
+    }
+}
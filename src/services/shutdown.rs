@@ -0,0 +1,176 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{info, warn};
+use crate::models::{Speaker, TranscriptSource};
+use super::{app_state, deepgram_ws, session_reaper::SessionEndReason, AppState};
+
+/// Frase con la que se despide a las llamadas que siguen vivas al vencer el plazo
+pub const SHUTDOWN_GOODBYE_KEY: &str = "shutdown_goodbye";
+
+const GOODBYE_PLAYBACK: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Tiempo para que Deepgram entregue los últimos transcripts y cierre
+const STT_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Ciclo de vida del proceso: en drenaje no se aceptan llamadas ni streams nuevos
+pub struct Lifecycle {
+    draining: AtomicBool,
+    stopped: watch::Sender<bool>,
+}
+
+impl Lifecycle {
+    pub fn new() -> Self {
+        let (stopped, _) = watch::channel(false);
+        Self {
+            draining: AtomicBool::new(false),
+            stopped,
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Resuelve cuando el drenaje terminó y el servidor va a cerrar (p. ej. para cortar SSE)
+    pub async fn stopped(&self) {
+        let mut rx = self.stopped.subscribe();
+        let _ = rx.wait_for(|stopped| *stopped).await;
+    }
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `SHUTDOWN_DRAIN_TIMEOUT_SECS`: cuánto se deja terminar solas a las llamadas en curso
+pub fn drain_timeout() -> Duration {
+    let secs = std::env::var("SHUTDOWN_DRAIN_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(25);
+    Duration::from_secs(secs)
+}
+
+/// Futuro para `with_graceful_shutdown`: espera SIGTERM (o Ctrl+C), drena las llamadas
+/// y solo entonces deja que el servidor cierre. Mientras tanto webhooks y streams
+/// abiertos se siguen atendiendo.
+pub async fn on_signal(state: Arc<AppState>) {
+    wait_for_signal().await;
+    drain(&state, drain_timeout()).await;
+    state.lifecycle.stopped.send_replace(true);
+    info!("👋 Drenaje terminado, cerrando servidor");
+}
+
+async fn wait_for_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!("⚠️ No se pudo escuchar SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("🛑 Ctrl+C recibido"),
+        _ = terminate => info!("🛑 SIGTERM recibido"),
+    }
+}
+
+async fn drain(state: &Arc<AppState>, timeout: Duration) {
+    state.lifecycle.draining.store(true, Ordering::SeqCst);
+    let deadline = tokio::time::Instant::now() + timeout;
+    info!("🚰 Drenando llamadas (plazo {}s): sin llamadas ni streams nuevos", timeout.as_secs());
+
+    loop {
+        let remaining = local_calls(state).await;
+        if remaining.is_empty() {
+            info!("✅ Sin llamadas en curso");
+            break;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            warn!("⏰ Plazo de drenaje vencido, despidiendo {} llamadas", remaining.len());
+            futures_util::future::join_all(remaining.iter().map(|call_id| say_goodbye(state, call_id))).await;
+            break;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+
+    // Los streams ya se cerraron; se espera a que sus sockets STT terminen limpio
+    let stt_deadline = tokio::time::Instant::now() + STT_CLOSE_TIMEOUT;
+    while deepgram_ws::open_connections() > 0 && tokio::time::Instant::now() < stt_deadline {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let open = deepgram_ws::open_connections();
+    if open > 0 {
+        warn!("⚠️ {} conexiones Deepgram sin cerrar al apagar", open);
+    }
+}
+
+/// Llamadas que atiende esta réplica. Con un almacén compartido (Redis) solo cuentan las
+/// que tienen trabajo local: las demás siguen en otras réplicas.
+async fn local_calls(state: &Arc<AppState>) -> Vec<String> {
+    let shared = state.sessions.name() != "memory";
+    state.sessions.all().await
+        .into_iter()
+        .map(|session| session.call_control_id)
+        .filter(|call_id| {
+            !shared
+                || state.streams.contains_key(call_id)
+                || state.turns.contains_key(call_id)
+                || state.call_queues.contains_key(call_id)
+        })
+        .collect()
+}
+
+async fn say_goodbye(state: &Arc<AppState>, call_id: &str) {
+    let Some(session) = state.sessions.get(call_id).await else {
+        return;
+    };
+    let language = session.language.unwrap_or_default();
+    if let Some(url) = state.get_or_generate_quick_reply(SHUTDOWN_GOODBYE_KEY, session.voz.as_ref(), language).await {
+        if state.telnyx_service.play_audio(call_id, &url).await.is_ok() {
+            if let Some(text) = app_state::quick_reply_text(SHUTDOWN_GOODBYE_KEY, language) {
+                state.record_transcript(call_id, Speaker::Bot, TranscriptSource::Phrase, text, None).await;
+            }
+            tokio::time::sleep(GOODBYE_PLAYBACK).await;
+        }
+    }
+    if let Err(e) = state.telnyx_service.hangup(call_id).await {
+        warn!("⚠️ [CALL:{}] Error colgando al apagar: {}", call_id, e);
+    }
+    state.end_call(call_id, SessionEndReason::Shutdown).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stopped_resolves_once_drain_finishes() {
+        let lifecycle = Lifecycle::new();
+        assert!(!lifecycle.is_draining());
+
+        lifecycle.draining.store(true, Ordering::SeqCst);
+        assert!(lifecycle.is_draining());
+
+        lifecycle.stopped.send_replace(true);
+        tokio::time::timeout(Duration::from_millis(100), lifecycle.stopped())
+            .await
+            .expect("stopped() debe resolver tras el drenaje");
+    }
+}
@@ -0,0 +1,253 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{info, error, debug};
+use crate::audio::vad::{EnergyVad, VadConfig, VadEvent};
+use crate::models::{CallState, Speaker, TranscriptSource};
+use super::{app_state, AppState};
+
+/// Frase en caché (`AppState::get_or_generate_quick_reply`) para el primer aviso
+pub const STILL_THERE_KEY: &str = "still_there";
+/// Frase de despedida antes de colgar por silencio
+pub const SILENCE_GOODBYE_KEY: &str = "silence_goodbye";
+
+/// Tiempo que se deja sonar la despedida antes de colgar
+const GOODBYE_PLAYBACK: Duration = Duration::from_secs(4);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SilenceAction {
+    /// Preguntar "¿Sigues ahí?"
+    Prompt,
+    /// Despedirse y colgar
+    HangUp,
+}
+
+/// Temporizador de silencio puro: sin reloj propio ni IO, para poder probarlo
+#[derive(Debug)]
+pub struct SilenceTracker {
+    prompt_after: Duration,
+    hangup_after: Duration,
+    last_activity: Instant,
+    prompted_at: Option<Instant>,
+}
+
+impl SilenceTracker {
+    pub fn new(prompt_after: Duration, hangup_after: Duration, now: Instant) -> Self {
+        Self { prompt_after, hangup_after, last_activity: now, prompted_at: None }
+    }
+
+    /// Habla del cliente: reinicia el temporizador y olvida el aviso
+    pub fn activity(&mut self, now: Instant) {
+        self.last_activity = now;
+        self.prompted_at = None;
+    }
+
+    /// Audio del bot (incluido el propio aviso): aplaza el aviso, pero no el cuelgue
+    /// si el cliente sigue sin contestar
+    pub fn bot_activity(&mut self, now: Instant) {
+        self.last_activity = now;
+    }
+
+    pub fn poll(&mut self, now: Instant) -> Option<SilenceAction> {
+        match self.prompted_at {
+            None if now.duration_since(self.last_activity) >= self.prompt_after => {
+                self.prompted_at = Some(now);
+                Some(SilenceAction::Prompt)
+            }
+            Some(prompted) if now.duration_since(prompted) >= self.hangup_after => Some(SilenceAction::HangUp),
+            _ => None,
+        }
+    }
+}
+
+/// VAD local + temporizador de silencio de una llamada en modo media stream
+pub struct SilenceMonitor {
+    tracker: Mutex<SilenceTracker>,
+    vad: Mutex<EnergyVad>,
+    speaking: AtomicBool,
+    /// Fin del último enunciado del cliente, para medir la latencia del STT
+    speech_ended_at: Mutex<Option<Instant>>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl SilenceMonitor {
+    /// Crea y registra el monitor de `call_id`. `None` si `SILENCE_TIMEOUT_MS=0`.
+    pub fn start(state: Arc<AppState>, call_id: String) -> Option<Arc<Self>> {
+        let prompt_ms = std::env::var("SILENCE_TIMEOUT_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(20000);
+        if prompt_ms == 0 {
+            return None;
+        }
+        let hangup_ms = std::env::var("SILENCE_HANGUP_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(10000);
+
+        let defaults = VadConfig::default();
+        let vad_config = VadConfig {
+            threshold_dbfs: std::env::var("VAD_THRESHOLD_DBFS")
+                .ok()
+                .and_then(|v| v.parse::<f64>().ok())
+                .unwrap_or(defaults.threshold_dbfs),
+            hangover_ms: std::env::var("VAD_HANGOVER_MS")
+                .ok()
+                .and_then(|v| v.parse::<u32>().ok())
+                .unwrap_or(defaults.hangover_ms),
+            ..defaults
+        };
+
+        let monitor = Arc::new(Self {
+            tracker: Mutex::new(SilenceTracker::new(
+                Duration::from_millis(prompt_ms),
+                Duration::from_millis(hangup_ms),
+                Instant::now(),
+            )),
+            vad: Mutex::new(EnergyVad::new(vad_config)),
+            speaking: AtomicBool::new(false),
+            speech_ended_at: Mutex::new(None),
+            task: Mutex::new(None),
+        });
+
+        let task = tokio::spawn(run(state.clone(), call_id.clone(), monitor.clone()));
+        *monitor.task.lock().unwrap() = Some(task);
+        state.silence_monitors.insert(call_id, monitor.clone());
+        Some(monitor)
+    }
+
+    /// Frame μ-law de la pista del cliente. Devuelve el cambio de estado del VAD
+    /// (`SpeechStart` sirve como señal de barge-in)
+    pub fn feed_ulaw(&self, ulaw: &[u8]) -> Option<VadEvent> {
+        let event = self.vad.lock().unwrap().process_ulaw(ulaw)?;
+        self.speaking.store(event == VadEvent::SpeechStart, Ordering::SeqCst);
+        if event == VadEvent::SpeechEnd {
+            *self.speech_ended_at.lock().unwrap() = Some(Instant::now());
+        }
+        self.touch();
+        Some(event)
+    }
+
+    /// Tiempo desde que el cliente dejó de hablar (una vez por enunciado)
+    pub fn take_speech_end_elapsed(&self) -> Option<Duration> {
+        self.speech_ended_at.lock().unwrap().take().map(|at| at.elapsed())
+    }
+
+    /// Reinicia el temporizador (el cliente habla o un operador lleva la llamada)
+    pub fn touch(&self) {
+        self.tracker.lock().unwrap().activity(Instant::now());
+    }
+
+    /// El bot habló o terminó de reproducir audio
+    pub fn touch_bot(&self) {
+        self.tracker.lock().unwrap().bot_activity(Instant::now());
+    }
+
+    pub fn stop(&self) {
+        if let Some(task) = self.task.lock().unwrap().take() {
+            task.abort();
+        }
+    }
+}
+
+async fn run(state: Arc<AppState>, call_id: String, monitor: Arc<SilenceMonitor>) {
+    let mut ticker = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        ticker.tick().await;
+
+        // Mientras el cliente habla no hay silencio que contar
+        if monitor.speaking.load(Ordering::SeqCst) {
+            monitor.touch();
+            continue;
+        }
+
+        let (voz, language) = match state.sessions.get(&call_id).await {
+            // Con la IA en pausa un operador lleva la llamada: el silencio no se cuenta
+            Some(session) if session.ai_paused => {
+                monitor.touch();
+                continue;
+            }
+            Some(session) => (session.voz.clone(), session.language.unwrap_or_default()),
+            None => {
+                debug!("🔇 [CALL:{}] Sesión cerrada, fin del monitor de silencio", call_id);
+                return;
+            }
+        };
+        let action = monitor.tracker.lock().unwrap().poll(Instant::now());
+
+        match action {
+            Some(SilenceAction::Prompt) => {
+                info!("🔇 [CALL:{}] Silencio prolongado, preguntando si sigue ahí", call_id);
+                if let Some(url) = state.get_or_generate_quick_reply(STILL_THERE_KEY, voz.as_ref(), language).await {
+                    match state.telnyx_service.play_audio(&call_id, &url).await {
+                        Ok(_) => {
+                            if let Some(text) = app_state::quick_reply_text(STILL_THERE_KEY, language) {
+                                state.record_transcript(&call_id, Speaker::Bot, TranscriptSource::Phrase, text, None).await;
+                            }
+                            state.set_call_state(&call_id, CallState::Speaking).await;
+                        }
+                        Err(e) => error!("❌ [CALL:{}] Error reproduciendo aviso de silencio: {}", call_id, e),
+                    }
+                }
+            }
+            Some(SilenceAction::HangUp) => {
+                info!("📵 [CALL:{}] Sin respuesta tras el aviso, colgando", call_id);
+                if let Some(url) = state.get_or_generate_quick_reply(SILENCE_GOODBYE_KEY, voz.as_ref(), language).await {
+                    if state.telnyx_service.play_audio(&call_id, &url).await.is_ok() {
+                        if let Some(text) = app_state::quick_reply_text(SILENCE_GOODBYE_KEY, language) {
+                            state.record_transcript(&call_id, Speaker::Bot, TranscriptSource::Phrase, text, None).await;
+                        }
+                        tokio::time::sleep(GOODBYE_PLAYBACK).await;
+                    }
+                }
+                if let Err(e) = state.telnyx_service.hangup(&call_id).await {
+                    error!("❌ [CALL:{}] Error colgando por silencio: {}", call_id, e);
+                }
+                return;
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompts_then_hangs_up_unless_activity() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut tracker = SilenceTracker::new(Duration::from_secs(20), Duration::from_secs(10), start);
+
+        assert_eq!(tracker.poll(at(19)), None);
+        assert_eq!(tracker.poll(at(20)), Some(SilenceAction::Prompt));
+        assert_eq!(tracker.poll(at(25)), None);
+
+        // El cliente contesta al aviso: vuelta a empezar
+        tracker.activity(at(26));
+        assert_eq!(tracker.poll(at(36)), None);
+        assert_eq!(tracker.poll(at(46)), Some(SilenceAction::Prompt));
+        assert_eq!(tracker.poll(at(56)), Some(SilenceAction::HangUp));
+    }
+
+    #[test]
+    fn bot_playback_does_not_cancel_pending_hangup() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut tracker = SilenceTracker::new(Duration::from_secs(20), Duration::from_secs(10), start);
+
+        // Playback del bot antes del aviso: lo aplaza
+        tracker.bot_activity(at(5));
+        assert_eq!(tracker.poll(at(20)), None);
+        assert_eq!(tracker.poll(at(25)), Some(SilenceAction::Prompt));
+
+        // playback.started / ended del propio aviso
+        tracker.bot_activity(at(25));
+        tracker.bot_activity(at(27));
+        assert_eq!(tracker.poll(at(34)), None);
+        assert_eq!(tracker.poll(at(35)), Some(SilenceAction::HangUp));
+    }
+}
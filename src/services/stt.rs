@@ -0,0 +1,116 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use crate::models::SttOverrides;
+use super::DeepgramWebSocket;
+use super::replay_stt::ReplayProvider;
+use super::telnyx_stt::TelnyxTranscriptSource;
+
+/// Evento de transcripción unificado, independiente del proveedor STT
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptEvent {
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub confidence: f64,
+    #[serde(default)]
+    pub is_final: bool,
+    /// El segmento final cierra el enunciado (endpointing del proveedor)
+    #[serde(default)]
+    pub speech_final: bool,
+    /// El proveedor detectó inicio de voz (útil para barge-in)
+    #[serde(default)]
+    pub speech_started: bool,
+    /// El proveedor detectó fin de enunciado
+    #[serde(default)]
+    pub utterance_end: bool,
+    /// Idioma detectado por el proveedor (p. ej. Deepgram con `language=multi`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+impl TranscriptEvent {
+    pub fn transcript(text: impl Into<String>, confidence: f64, is_final: bool) -> Self {
+        Self {
+            text: text.into(),
+            confidence,
+            is_final,
+            ..Default::default()
+        }
+    }
+
+    /// Enunciado completo (final + fin de turno), para proveedores sin eventos de turno
+    pub fn final_utterance(text: impl Into<String>, confidence: f64) -> Self {
+        Self {
+            speech_final: true,
+            ..Self::transcript(text, confidence, true)
+        }
+    }
+
+    pub fn speech_started() -> Self {
+        Self { speech_started: true, ..Default::default() }
+    }
+
+    pub fn utterance_end() -> Self {
+        Self { utterance_end: true, ..Default::default() }
+    }
+
+    pub fn has_text(&self) -> bool {
+        !self.text.trim().is_empty()
+    }
+}
+
+/// Conexión STT de una llamada: audio μ-law 8 kHz hacia el proveedor, eventos de vuelta.
+/// Cerrar `audio_tx` (soltarlo) termina la sesión con el proveedor.
+pub struct SttStream {
+    pub audio_tx: mpsc::Sender<Vec<u8>>,
+    pub events: mpsc::Receiver<TranscriptEvent>,
+}
+
+#[async_trait]
+pub trait SttProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Si puede abrir un segundo stream para transcribir la pista outbound (voz del bot)
+    fn supports_outbound(&self) -> bool {
+        true
+    }
+
+    /// Idioma fijo con el que transcribe, si lo hay. `None` o "multi" = no hace falta
+    /// reconectar al cambiar el idioma de la llamada.
+    fn language(&self, _overrides: Option<&SttOverrides>) -> Option<String> {
+        None
+    }
+
+    /// `overrides` son los ajustes de STT de la llamada (idioma, modelo, vocabulario...);
+    /// cada proveedor aplica los que soporta.
+    async fn connect(&self, call_id: String, overrides: Option<&SttOverrides>) -> anyhow::Result<SttStream>;
+}
+
+/// Selecciona el proveedor con `STT_PROVIDER` (deepgram | telnyx | replay)
+pub fn provider_from_env(
+    telnyx_transcripts: Arc<TelnyxTranscriptSource>,
+) -> Arc<dyn SttProvider> {
+    let name = std::env::var("STT_PROVIDER").unwrap_or_else(|_| "deepgram".to_string());
+
+    let provider: Arc<dyn SttProvider> = match name.to_lowercase().as_str() {
+        "telnyx" => telnyx_transcripts,
+        "replay" => match ReplayProvider::from_env() {
+            Ok(replay) => Arc::new(replay),
+            Err(e) => {
+                warn!("⚠️ STT replay no disponible ({}), usando Deepgram", e);
+                Arc::new(DeepgramWebSocket::new())
+            }
+        },
+        "deepgram" => Arc::new(DeepgramWebSocket::new()),
+        other => {
+            warn!("⚠️ STT_PROVIDER desconocido '{}', usando Deepgram", other);
+            Arc::new(DeepgramWebSocket::new())
+        }
+    };
+
+    info!("🎧 Proveedor STT: {}", provider.name());
+    provider
+}
@@ -1,8 +1,8 @@
 use serde::{Deserialize, Serialize};
 use reqwest::Client;
-use tracing::{info, error, debug};
+use tracing::{info, error, debug, warn};
 use base64::{engine::general_purpose::STANDARD, Engine};
-use crate::models::{ClientState, CallResponse};
+use crate::models::{CallState, ClientState, CallResponse, Language};
 
 #[derive(Clone)]
 pub struct TelnyxService {
@@ -82,43 +82,30 @@ impl TelnyxService {
     pub async fn initiate_call(
         &self,
         to: &str,
-        nombre: &str,
-        telefono: &str,
-        contexto: Option<&str>,
+        client_state: &ClientState,
     ) -> anyhow::Result<CallResponse> {
-        self.initiate_call_internal(to, nombre, telefono, contexto, false).await
+        self.initiate_call_internal(to, client_state, false).await
     }
 
     /// Iniciar llamada con WebSocket Media Streams
     pub async fn initiate_call_with_stream(
         &self,
         to: &str,
-        nombre: &str,
-        telefono: &str,
-        contexto: Option<&str>,
+        client_state: &ClientState,
     ) -> anyhow::Result<CallResponse> {
-        self.initiate_call_internal(to, nombre, telefono, contexto, true).await
+        self.initiate_call_internal(to, client_state, true).await
     }
 
     async fn initiate_call_internal(
         &self,
         to: &str,
-        nombre: &str,
-        telefono: &str,
-        contexto: Option<&str>,
+        client_state: &ClientState,
         use_stream: bool,
     ) -> anyhow::Result<CallResponse> {
         let webhook_url = std::env::var("WEBHOOK_BASE_URL")
             .unwrap_or_else(|_| "https://your-domain.com".to_string());
 
-        let client_state = ClientState {
-            nombre: nombre.to_string(),
-            telefono: telefono.to_string(),
-            contexto: contexto.map(|s| s.to_string()),
-            call_control_id: None,
-        };
-
-        let client_state_encoded = STANDARD.encode(serde_json::to_string(&client_state)?);
+        let client_state_encoded = STANDARD.encode(serde_json::to_string(client_state)?);
 
         let mut payload = InitiateCallPayload {
             connection_id: self.connection_id.clone(),
@@ -143,9 +130,7 @@ impl TelnyxService {
                 }
             };
             payload.stream_url = Some(stream_url);
-            // Track válido: "inbound" o "outbound"
-            let track = std::env::var("STREAM_TRACK").unwrap_or_else(|_| "inbound".to_string());
-            payload.stream_track = Some(track);
+            payload.stream_track = Some(Self::stream_track());
             info!("🔌 Iniciando llamada con Media Stream: {}", payload.stream_url.as_ref().unwrap());
         };
 
@@ -187,10 +172,23 @@ impl TelnyxService {
             call_control_id: data.call_control_id,
             call_id,
             status: data.status.unwrap_or_else(|| "initiated".to_string()),
+            state: CallState::Dialing,
             timestamp: chrono::Utc::now(),
         })
     }
 
+    /// Pista del media stream: "inbound" (solo cliente), "outbound" o "both_tracks"
+    pub fn stream_track() -> String {
+        match std::env::var("STREAM_TRACK") {
+            Ok(track) if matches!(track.as_str(), "inbound" | "outbound" | "both_tracks") => track,
+            Ok(track) => {
+                warn!("⚠️ STREAM_TRACK inválido '{}', usando inbound", track);
+                "inbound".to_string()
+            }
+            Err(_) => "inbound".to_string(),
+        }
+    }
+
     /// Inicia Media Streams en una llamada activa usando Call Command streaming_start
     pub async fn start_media_stream(&self, call_control_id: &str) -> anyhow::Result<()> {
         let webhook_url = std::env::var("WEBHOOK_BASE_URL")
@@ -201,7 +199,7 @@ impl TelnyxService {
 
         let payload = StreamingStartPayload {
             stream_url: stream_url.clone(),
-            stream_track: std::env::var("STREAM_TRACK").unwrap_or_else(|_| "inbound".to_string()),
+            stream_track: Self::stream_track(),
             codec: Some("PCMU".to_string()),
             sample_rate: Some(8000),
             channels: Some(1),
@@ -226,22 +224,32 @@ impl TelnyxService {
         Ok(())
     }
 
+    /// `playback_id` vuelve en el `client_state` de `call.speak.ended`
     pub async fn speak(
         &self,
         call_control_id: &str,
         message: &str,
+        language: Language,
+        playback_id: Option<&str>,
     ) -> anyhow::Result<()> {
         #[derive(Serialize)]
         struct SpeakPayload {
             payload: String,
             voice: String,
             language: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            client_state: Option<String>,
         }
 
+        let client_state = playback_id
+            .map(|id| serde_json::to_string(&serde_json::json!({ "playback_id": id })))
+            .transpose()?
+            .map(|json| STANDARD.encode(json));
         let payload = SpeakPayload {
             payload: message.to_string(),
             voice: "female".to_string(),
-            language: "es-MX".to_string(),
+            language: language.locale().to_string(),
+            client_state,
         };
 
         let response = self.client
@@ -266,6 +274,16 @@ impl TelnyxService {
         &self,
         call_control_id: &str,
         audio_url: &str,
+    ) -> anyhow::Result<()> {
+        self.play_audio_tagged(call_control_id, audio_url, None).await
+    }
+
+    /// `playback_id` vuelve en el `client_state` de `call.playback.ended`
+    pub async fn play_audio_tagged(
+        &self,
+        call_control_id: &str,
+        audio_url: &str,
+        playback_id: Option<&str>,
     ) -> anyhow::Result<()> {
         #[derive(Serialize)]
         struct PlaybackPayload {
@@ -278,7 +296,10 @@ impl TelnyxService {
         }
 
         // Preparar client_state para permitir barge-in/interrupción si es necesario
-        let client_state_json = serde_json::json!({ "interruptible": true });
+        let mut client_state_json = serde_json::json!({ "interruptible": true });
+        if let Some(id) = playback_id {
+            client_state_json["playback_id"] = id.into();
+        }
         let client_state_b64 = base64::engine::general_purpose::STANDARD.encode(serde_json::to_string(&client_state_json)?);
 
         let payload = PlaybackPayload {
@@ -307,7 +328,8 @@ impl TelnyxService {
         Ok(())
     }
 
-    pub async fn start_transcription(&self, call_control_id: &str) -> anyhow::Result<()> {
+    /// `language` sustituye a `TELNYX_TRANSCRIPTION_LANG` (cambio de idioma a mitad de llamada)
+    pub async fn start_transcription(&self, call_control_id: &str, language: Option<Language>) -> anyhow::Result<()> {
         let webhook_url = std::env::var("WEBHOOK_BASE_URL")
             .unwrap_or_else(|_| "https://your-domain.com".to_string());
 
@@ -323,8 +345,10 @@ impl TelnyxService {
         // Permitir alternar motor e idioma vía env vars
         let engine = std::env::var("TELNYX_TRANSCRIPTION_ENGINE")
             .unwrap_or_else(|_| "google".to_string());
-        let language = std::env::var("TELNYX_TRANSCRIPTION_LANG")
-            .unwrap_or_else(|_| "es".to_string());
+        let language = match language {
+            Some(language) => language.code().to_string(),
+            None => std::env::var("TELNYX_TRANSCRIPTION_LANG").unwrap_or_else(|_| "es".to_string()),
+        };
 
         // Opciones de Deepgram para reducir latencia (endpoint_silence_ms)
         let engine_options = if engine == "deepgram" {
@@ -365,6 +389,25 @@ impl TelnyxService {
         Ok(())
     }
 
+    pub async fn stop_transcription(&self, call_control_id: &str) -> anyhow::Result<()> {
+        let response = self.client
+            .post(format!("{}/calls/{}/actions/transcription_stop", self.base_url, call_control_id))
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({}))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("❌ [CALL:{}] Error deteniendo transcripción (status {}): {}", call_control_id, status, body);
+            return Err(anyhow::anyhow!("Failed to stop transcription"));
+        }
+
+        info!("⏹️ [CALL:{}] Transcripción detenida", call_control_id);
+        Ok(())
+    }
+
     pub async fn hangup(&self, call_control_id: &str) -> anyhow::Result<()> {
         let response = self.client
             .post(format!("{}/calls/{}/actions/hangup", self.base_url, call_control_id))
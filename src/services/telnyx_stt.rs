@@ -0,0 +1,87 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use dashmap::DashMap;
+use tokio::sync::mpsc;
+use tracing::{info, debug};
+use crate::models::{Language, SttOverrides};
+use super::TelnyxService;
+use super::stt::{SttProvider, SttStream, TranscriptEvent};
+
+/// Transcripción nativa de Telnyx (`transcription_start`) expuesta como `SttProvider`.
+/// El audio del media stream se descarta: Telnyx transcribe del lado del servidor y los
+/// resultados llegan por webhook, desde donde `push` los entrega al consumidor de la llamada.
+pub struct TelnyxTranscriptSource {
+    telnyx_service: TelnyxService,
+    streams: Arc<DashMap<String, mpsc::Sender<TranscriptEvent>>>,
+}
+
+impl TelnyxTranscriptSource {
+    pub fn new(telnyx_service: TelnyxService) -> Self {
+        Self {
+            telnyx_service,
+            streams: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Entrega un evento del webhook. Devuelve `false` si la llamada no tiene consumidor
+    /// (modo webhook clásico), para que el handler siga con su propio pipeline.
+    pub async fn push(&self, call_id: &str, event: TranscriptEvent) -> bool {
+        let sender = match self.streams.get(call_id) {
+            Some(sender) => sender.clone(),
+            None => return false,
+        };
+
+        if sender.send(event).await.is_err() {
+            self.streams.remove(call_id);
+            return false;
+        }
+        true
+    }
+}
+
+#[async_trait]
+impl SttProvider for TelnyxTranscriptSource {
+    fn name(&self) -> &'static str {
+        "telnyx"
+    }
+
+    /// Telnyx transcribe la llamada por call_control_id: un solo stream por llamada
+    fn supports_outbound(&self) -> bool {
+        false
+    }
+
+    /// Telnyx transcribe con idioma fijo: `TELNYX_TRANSCRIPTION_LANG` o el de la llamada
+    fn language(&self, overrides: Option<&SttOverrides>) -> Option<String> {
+        overrides
+            .and_then(|o| o.language.clone())
+            .or_else(|| std::env::var("TELNYX_TRANSCRIPTION_LANG").ok())
+            .or_else(|| Some("es".to_string()))
+    }
+
+    /// Para cambiar de idioma hay que detener antes la transcripción en curso (`stop_transcription`)
+    async fn connect(&self, call_id: String, overrides: Option<&SttOverrides>) -> anyhow::Result<SttStream> {
+        let (event_tx, events) = mpsc::channel::<TranscriptEvent>(100);
+        let (audio_tx, mut audio_rx) = mpsc::channel::<Vec<u8>>(100);
+        let language = overrides
+            .and_then(|o| o.language.as_deref())
+            .and_then(Language::from_code);
+
+        self.streams.insert(call_id.clone(), event_tx.clone());
+        if let Err(e) = self.telnyx_service.start_transcription(&call_id, language).await {
+            self.streams.remove(&call_id);
+            return Err(e);
+        }
+        info!("✅ [CALL:{}][STT] Transcripción Telnyx vinculada al media stream", call_id);
+
+        // Consumir (y descartar) el audio; al cerrarse el canal se desregistra la llamada,
+        // salvo que un reinicio (cambio de idioma) ya haya registrado otro consumidor
+        let streams = self.streams.clone();
+        tokio::spawn(async move {
+            while audio_rx.recv().await.is_some() {}
+            streams.remove_if(&call_id, |_, sender| sender.same_channel(&event_tx));
+            debug!("🔚 [CALL:{}][STT] Transcripción Telnyx desvinculada", call_id);
+        });
+
+        Ok(SttStream { audio_tx, events })
+    }
+}
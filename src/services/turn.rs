@@ -0,0 +1,440 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info};
+use crate::models::{CallState, LatencyStage, Speaker, TranscriptSource};
+use super::{app_state, AppState, SessionManager, TranscriptEvent};
+
+/// Frase en caché para pedir al cliente que repita
+pub const REPEAT_PLEASE_KEY: &str = "repeat_please";
+
+/// `TRANSCRIPTION_MIN_CONFIDENCE`: segmentos finales por debajo no llegan al LLM
+pub fn min_confidence() -> f64 {
+    std::env::var("TRANSCRIPTION_MIN_CONFIDENCE")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .unwrap_or(0.6)
+}
+
+/// `LOW_CONFIDENCE_REPROMPT_AFTER`: turnos ininteligibles seguidos antes de pedir que repita
+pub fn reprompt_after() -> u32 {
+    std::env::var("LOW_CONFIDENCE_REPROMPT_AFTER")
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(2)
+        .max(1)
+}
+
+/// Dos finales con el mismo texto dentro de esta ventana son el mismo turno
+/// (reintentos de webhook, final repetido por el proveedor)
+const DUPLICATE_FINAL_WINDOW: Duration = Duration::from_secs(3);
+
+/// Turno completo del llamante, listo para enviarse al LLM
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletedTurn {
+    pub text: String,
+    /// Confianza promedio de los segmentos finales del turno
+    pub confidence: f64,
+    /// Último idioma informado por el proveedor en el turno
+    pub language: Option<String>,
+}
+
+/// Resultado de cerrar un turno
+#[derive(Debug, Clone, PartialEq)]
+pub enum TurnOutcome {
+    Completed(CompletedTurn),
+    /// Todos los segmentos finales quedaron bajo la confianza mínima
+    Unclear { text: String, confidence: f64 },
+}
+
+/// Acumula segmentos `is_final` hasta que el proveedor marca fin de turno
+/// (`speech_final` o `UtteranceEnd`). Los interims solo informan, no cierran turnos.
+/// Los finales con confianza menor a `min_confidence` se apartan del texto del turno.
+#[derive(Debug, Default)]
+pub struct TurnManager {
+    min_confidence: f64,
+    segments: Vec<String>,
+    confidence_sum: f64,
+    language: Option<String>,
+    rejected: Vec<(String, f64)>,
+}
+
+impl TurnManager {
+    pub fn with_min_confidence(min_confidence: f64) -> Self {
+        Self { min_confidence, ..Self::default() }
+    }
+
+    /// Procesa un evento; devuelve el resultado del turno si este evento lo cierra
+    pub fn push(&mut self, event: &TranscriptEvent) -> Option<TurnOutcome> {
+        if event.has_text() {
+            if !event.is_final {
+                return None;
+            }
+            if event.confidence < self.min_confidence {
+                self.rejected.push((event.text.trim().to_string(), event.confidence));
+            } else {
+                self.segments.push(event.text.trim().to_string());
+                self.confidence_sum += event.confidence;
+                if event.language.is_some() {
+                    self.language = event.language.clone();
+                }
+            }
+            if event.speech_final {
+                return self.take();
+            }
+        }
+
+        if event.utterance_end {
+            return self.take();
+        }
+
+        None
+    }
+
+    /// Texto que tendría el turno si `event` lo cerrara (finales acumulados + este segmento)
+    pub fn preview(&self, event: &TranscriptEvent) -> Option<String> {
+        if !event.has_text() {
+            return None;
+        }
+        let mut parts: Vec<&str> = self.segments.iter().map(String::as_str).collect();
+        parts.push(event.text.trim());
+        Some(parts.join(" "))
+    }
+
+    fn take(&mut self) -> Option<TurnOutcome> {
+        let rejected = std::mem::take(&mut self.rejected);
+        if self.segments.is_empty() {
+            if rejected.is_empty() {
+                return None;
+            }
+            let confidence = rejected.iter().map(|(_, c)| c).sum::<f64>() / rejected.len() as f64;
+            let text = rejected.into_iter().map(|(t, _)| t).collect::<Vec<_>>().join(" ");
+            return Some(TurnOutcome::Unclear { text, confidence });
+        }
+
+        let count = self.segments.len() as f64;
+        let turn = CompletedTurn {
+            text: self.segments.join(" "),
+            confidence: self.confidence_sum / count,
+            language: self.language.take(),
+        };
+        self.segments.clear();
+        self.confidence_sum = 0.0;
+        Some(TurnOutcome::Completed(turn))
+    }
+}
+
+/// Respuesta del LLM lanzada a partir de un interim, antes de que llegue el final
+pub type Speculation = JoinHandle<anyhow::Result<String>>;
+
+/// Identifica el turno del llamante al que pertenece una respuesta
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TurnTicket(u64);
+
+/// Cómo obtener la respuesta de un turno recién cerrado
+pub enum TurnStart {
+    /// El final coincide con la especulación en curso: se reutiliza su respuesta
+    Reuse(TurnTicket, Speculation),
+    /// Hay que llamar al LLM con el texto final
+    Fresh(TurnTicket),
+}
+
+impl TurnStart {
+    pub fn ticket(&self) -> TurnTicket {
+        match self {
+            TurnStart::Reuse(ticket, _) | TurnStart::Fresh(ticket) => *ticket,
+        }
+    }
+
+    /// Descarta la respuesta especulativa (p. ej. el turno cambió el idioma de la llamada)
+    pub fn without_speculation(self) -> Self {
+        match self {
+            TurnStart::Reuse(ticket, task) => {
+                task.abort();
+                TurnStart::Fresh(ticket)
+            }
+            fresh => fresh,
+        }
+    }
+
+    /// Respuesta del turno: la especulativa si terminó bien, si no `fresh`
+    pub async fn into_response<F>(self, fresh: F) -> anyhow::Result<String>
+    where
+        F: Future<Output = anyhow::Result<String>>,
+    {
+        if let TurnStart::Reuse(_, task) = self {
+            match task.await {
+                Ok(Ok(response)) => return Ok(response),
+                Ok(Err(e)) => debug!("♻️ Especulación fallida, se llama de nuevo al LLM: {}", e),
+                Err(e) => debug!("♻️ Especulación cancelada, se llama de nuevo al LLM: {}", e),
+            }
+        }
+        fresh.await
+    }
+}
+
+/// Garantiza como mucho una respuesta por turno del llamante en una llamada.
+/// Los interims pueden lanzar una respuesta especulativa; cuando llega el final se
+/// reutiliza si el texto coincide o se aborta si cambió.
+pub struct TurnCoordinator {
+    /// Palabras mínimas de un interim para especular (0 = sin especulación)
+    min_words: usize,
+    inner: Mutex<CoordinatorState>,
+}
+
+#[derive(Default)]
+struct CoordinatorState {
+    turn: u64,
+    speculation: Option<(String, Speculation)>,
+    last_final: Option<(String, Instant)>,
+}
+
+impl TurnCoordinator {
+    pub fn new(min_words: usize) -> Self {
+        Self {
+            min_words,
+            inner: Mutex::new(CoordinatorState::default()),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let enabled = std::env::var("SPECULATIVE_LLM_ENABLED")
+            .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+            .unwrap_or(true);
+        let min_words = std::env::var("SPECULATIVE_MIN_WORDS")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(4)
+            .max(1);
+        Self::new(if enabled { min_words } else { 0 })
+    }
+
+    /// Lanza `start` para un interim suficientemente largo, salvo que ya haya una
+    /// especulación para el mismo texto. Una especulación anterior distinta se aborta.
+    pub fn speculate<F>(&self, text: &str, start: F) -> bool
+    where
+        F: FnOnce() -> Speculation,
+    {
+        if self.min_words == 0 || text.split_whitespace().count() < self.min_words {
+            return false;
+        }
+
+        let key = normalize(text);
+        let mut state = self.inner.lock().unwrap();
+        if matches!(&state.speculation, Some((current, _)) if *current == key) {
+            return false;
+        }
+        if let Some((_, stale)) = state.speculation.take() {
+            stale.abort();
+        }
+        state.speculation = Some((key, start()));
+        true
+    }
+
+    /// Cierra el turno con su texto final. `None` si está vacío o repite el turno anterior.
+    pub fn finalize(&self, text: &str) -> Option<TurnStart> {
+        let key = normalize(text);
+        if key.is_empty() {
+            return None;
+        }
+
+        let mut state = self.inner.lock().unwrap();
+        if matches!(&state.last_final, Some((last, at)) if *last == key && at.elapsed() < DUPLICATE_FINAL_WINDOW) {
+            return None;
+        }
+
+        state.turn += 1;
+        state.last_final = Some((key.clone(), Instant::now()));
+        let ticket = TurnTicket(state.turn);
+
+        match state.speculation.take() {
+            Some((speculated, task)) if speculated == key => Some(TurnStart::Reuse(ticket, task)),
+            Some((_, task)) => {
+                task.abort();
+                Some(TurnStart::Fresh(ticket))
+            }
+            None => Some(TurnStart::Fresh(ticket)),
+        }
+    }
+
+    /// `false` si ya se cerró un turno posterior: la respuesta llegó tarde y se descarta
+    pub fn is_current(&self, ticket: TurnTicket) -> bool {
+        self.inner.lock().unwrap().turn == ticket.0
+    }
+
+    /// Aborta el trabajo especulativo pendiente (fin de llamada)
+    pub fn cancel(&self) {
+        if let Some((_, task)) = self.inner.lock().unwrap().speculation.take() {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for TurnCoordinator {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Registra un turno ininteligible de la llamada y, si ya van `reprompt_after()`
+/// seguidos, pide al cliente que repita con la frase en caché
+pub async fn handle_unclear_turn(state: &Arc<AppState>, call_id: &str, text: &str, confidence: f64) {
+    state.record_transcript(call_id, Speaker::Caller, TranscriptSource::SttFinal, text, Some(confidence)).await;
+    let recorded = state.sessions.update(call_id, |session| {
+        let streak = SessionManager::record_low_confidence(session);
+        (streak, session.low_confidence_turns, session.voz.clone(), session.language.unwrap_or_default(), session.ai_paused)
+    }).await;
+    let Some((streak, total, voz, language, paused)) = recorded else {
+        return;
+    };
+    info!(
+        "🔉 [CALL:{}] Turno con confianza baja ({:.2}): '{}' ({} seguidos, {} en la llamada)",
+        call_id, confidence, text, streak, total
+    );
+
+    // Con la IA en pausa el bot no pide que repita
+    if paused || streak < reprompt_after() {
+        return;
+    }
+    state.sessions.update(call_id, SessionManager::clear_low_confidence_streak).await;
+    if let Some(url) = state.get_or_generate_quick_reply(REPEAT_PLEASE_KEY, voz.as_ref(), language).await {
+        match state.telnyx_service.play_audio(call_id, &url).await {
+            Ok(_) => {
+                if let Some(phrase) = app_state::quick_reply_text(REPEAT_PLEASE_KEY, language) {
+                    state.record_transcript(call_id, Speaker::Bot, TranscriptSource::Phrase, phrase, None).await;
+                }
+                state.set_call_state(call_id, CallState::Speaking).await;
+            }
+            Err(e) => error!("❌ [CALL:{}] Error pidiendo que repita: {}", call_id, e),
+        }
+    }
+}
+
+/// Respuesta del LLM con el contexto actual de la sesión
+pub async fn generate_response(state: Arc<AppState>, call_id: String, text: String) -> anyhow::Result<String> {
+    let session = state.sessions
+        .get(&call_id)
+        .await
+        .ok_or_else(|| anyhow::anyhow!("Sesión no encontrada"))?;
+    // También frena las especulaciones lanzadas justo antes de pausar
+    if session.ai_paused {
+        anyhow::bail!("IA en pausa");
+    }
+    let (nombre, context, language) = (
+        session.nombre.clone(),
+        SessionManager::get_conversation_context(&session),
+        session.language.unwrap_or_default(),
+    );
+    let started = Instant::now();
+    let reply = state.claude_service
+        .generate_response(&text, &nombre, if context.is_empty() { None } else { Some(&context) }, language)
+        .await?;
+
+    // También cuentan las especulaciones descartadas: son tokens consumidos
+    let latency_ms = started.elapsed().as_millis() as u64;
+    state.record_latency(&call_id, LatencyStage::Llm, latency_ms).await;
+    state.sessions.update(&call_id, |session| {
+        session.metrics.input_tokens += reply.input_tokens.max(0) as u64;
+        session.metrics.output_tokens += reply.output_tokens.max(0) as u64;
+    }).await;
+    Ok(reply.text)
+}
+
+/// Lanza `generate_response` en segundo plano (para especular desde un interim)
+pub fn spawn_response(state: Arc<AppState>, call_id: String, text: String) -> Speculation {
+    tokio::spawn(generate_response(state, call_id, text))
+}
+
+/// Minúsculas, sin puntuación y con espacios colapsados: los finales suelen llegar
+/// con puntuación que el interim no tenía
+fn normalize(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_alphanumeric() { c.to_lowercase().next().unwrap_or(c) } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accumulates_finals_until_speech_final() {
+        let mut turns = TurnManager::default();
+        assert_eq!(turns.push(&TranscriptEvent::speech_started()), None);
+        assert_eq!(turns.push(&TranscriptEvent::transcript("quiero una", 0.8, false)), None);
+        assert_eq!(turns.push(&TranscriptEvent::transcript("quiero una cita", 0.8, true)), None);
+
+        let closing = TranscriptEvent {
+            speech_final: true,
+            ..TranscriptEvent::transcript("para mañana", 1.0, true)
+        };
+        let Some(TurnOutcome::Completed(turn)) = turns.push(&closing) else {
+            panic!("el speech_final debía cerrar el turno");
+        };
+        assert_eq!(turn.text, "quiero una cita para mañana");
+        assert!((turn.confidence - 0.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn utterance_end_closes_pending_segments_once() {
+        let mut turns = TurnManager::default();
+        turns.push(&TranscriptEvent::transcript("hola", 0.9, true));
+        assert!(matches!(
+            turns.push(&TranscriptEvent::utterance_end()),
+            Some(TurnOutcome::Completed(CompletedTurn { text, .. })) if text == "hola"
+        ));
+        // Un UtteranceEnd tras un speech_final no produce un turno vacío
+        assert_eq!(turns.push(&TranscriptEvent::utterance_end()), None);
+    }
+
+    #[test]
+    fn low_confidence_segments_are_kept_out_of_the_turn() {
+        let mut turns = TurnManager::with_min_confidence(0.6);
+        turns.push(&TranscriptEvent::transcript("mmm eh", 0.3, true));
+        turns.push(&TranscriptEvent::transcript("quiero una cita", 0.9, true));
+        assert!(matches!(
+            turns.push(&TranscriptEvent::utterance_end()),
+            Some(TurnOutcome::Completed(CompletedTurn { text, .. })) if text == "quiero una cita"
+        ));
+
+        let unclear = turns.push(&TranscriptEvent::final_utterance("ksh prr", 0.2));
+        assert_eq!(unclear, Some(TurnOutcome::Unclear { text: "ksh prr".to_string(), confidence: 0.2 }));
+    }
+
+    fn speculation(response: &str) -> Speculation {
+        let response = response.to_string();
+        tokio::spawn(async move { Ok(response) })
+    }
+
+    #[tokio::test]
+    async fn final_reuses_matching_speculation() {
+        let turns = TurnCoordinator::new(4);
+        assert!(!turns.speculate("quiero una", || speculation("corta")));
+        assert!(turns.speculate("quiero una cita mañana", || speculation("respuesta")));
+        // El mismo interim repetido no lanza otra llamada
+        assert!(!turns.speculate("Quiero una cita mañana", || speculation("otra")));
+
+        let start = turns.finalize("Quiero una cita, mañana.").unwrap();
+        assert!(matches!(start, TurnStart::Reuse(..)));
+        let response = start.into_response(async { Ok("nueva".to_string()) }).await.unwrap();
+        assert_eq!(response, "respuesta");
+    }
+
+    #[tokio::test]
+    async fn changed_final_discards_speculation_and_duplicates_are_dropped() {
+        let turns = TurnCoordinator::new(4);
+        turns.speculate("quiero una cita mañana", || speculation("vieja"));
+
+        let first = turns.finalize("quiero una cita el lunes").unwrap();
+        assert!(matches!(first, TurnStart::Fresh(_)));
+        assert!(turns.finalize("Quiero una cita el lunes").is_none());
+
+        let second = turns.finalize("a las diez").unwrap();
+        assert!(!turns.is_current(first.ticket()));
+        assert!(turns.is_current(second.ticket()));
+    }
+}
@@ -1,7 +1,5 @@
 #[cfg(test)]
 mod tests {
-    use super::*;
-
     #[tokio::test]
     async fn test_environment_loaded() {
         dotenv::dotenv().ok();
@@ -19,14 +17,16 @@ mod tests {
 
     #[test]
     fn test_client_state_serialization() {
-        use serde_json;
-        use crate::models::ClientState;
+        use telnyx_ai_service::models::ClientState;
 
         let state = ClientState {
             nombre: "Test".to_string(),
             telefono: "+12345678".to_string(),
             contexto: Some("Test context".to_string()),
             call_control_id: Some("test_call_id".to_string()),
+            voz: None,
+            stt: None,
+            grabacion: None,
         };
 
         let json = serde_json::to_string(&state).unwrap();
@@ -34,4 +34,113 @@ mod tests {
         assert!(json.contains("+12345678"));
         assert!(json.contains("test_call_id"));
     }
+
+    #[test]
+    fn test_client_state_from_stream_start() {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+        use telnyx_ai_service::models::ClientState;
+
+        let encoded = STANDARD.encode(r#"{"nombre":"Ana","telefono":"+57300","contexto":null,"call_control_id":null}"#);
+        let state = ClientState::decode(&encoded).unwrap();
+        assert_eq!(state.nombre, "Ana");
+        assert!(ClientState::decode("no es base64").is_none());
+
+        let custom = serde_json::json!({"contexto": "Vacuna de Rocky", "grabacion": "true"});
+        let state = state.with_custom_parameters(&custom);
+        assert_eq!(state.telefono, "+57300");
+        assert_eq!(state.contexto.as_deref(), Some("Vacuna de Rocky"));
+        assert_eq!(state.grabacion, Some(true));
+    }
+
+    #[test]
+    fn test_call_state_transitions() {
+        use telnyx_ai_service::models::CallState::*;
+
+        assert!(Dialing.can_transition_to(Answered));
+        assert!(Answered.can_transition_to(Greeting));
+        assert!(Greeting.can_transition_to(Listening));
+        assert!(Listening.can_transition_to(Thinking));
+        assert!(Thinking.can_transition_to(Speaking));
+        assert!(Speaking.can_transition_to(Thinking), "el cliente puede interrumpir al bot");
+        assert!(Speaking.can_transition_to(Ended));
+
+        assert!(!Dialing.can_transition_to(Speaking));
+        assert!(!Listening.can_transition_to(Greeting));
+        assert!(!Ended.can_transition_to(Listening));
+        assert!(!Ended.can_transition_to(Ended));
+    }
+
+    #[test]
+    fn test_transcript_text_and_sources() {
+        use telnyx_ai_service::models::{CallTranscript, Speaker, TranscriptLine, TranscriptSource};
+
+        let at = chrono::DateTime::parse_from_rfc3339("2025-03-01T15:04:05Z").unwrap().with_timezone(&chrono::Utc);
+        let line = |speaker, source, text: &str| TranscriptLine { speaker, source, text: text.into(), confidence: None, timestamp: at };
+        let transcript = CallTranscript {
+            call_control_id: "c1".into(),
+            active: false,
+            lines: vec![
+                line(Speaker::Caller, TranscriptSource::SttFinal, "Hola"),
+                line(Speaker::Caller, TranscriptSource::Dtmf, "1"),
+                line(Speaker::Bot, TranscriptSource::Llm, "¿En qué te ayudo?"),
+            ],
+        };
+        assert_eq!(
+            transcript.to_text(),
+            "[15:04:05] Cliente: Hola\n[15:04:05] Cliente (DTMF): 1\n[15:04:05] Bot: ¿En qué te ayudo?\n"
+        );
+
+        let json = serde_json::to_value(&transcript).unwrap();
+        assert_eq!(json["lines"][2]["source"], "llm");
+        // Líneas guardadas antes de existir el campo se leen como finales del STT
+        let legacy: TranscriptLine = serde_json::from_str(r#"{"speaker":"caller","text":"Hola","confidence":0.9,"timestamp":"2025-03-01T15:04:05Z"}"#).unwrap();
+        assert_eq!(legacy.source, TranscriptSource::SttFinal);
+        assert_eq!(legacy.confidence, Some(0.9));
+    }
+
+    #[test]
+    fn test_call_summary_from_session() {
+        use telnyx_ai_service::models::{CallState, CallSummary, SessionInfo};
+
+        let session: SessionInfo = serde_json::from_value(serde_json::json!({
+            "call_control_id": "c1",
+            "nombre": "Ana",
+            "telefono": "+57300",
+            "created_at": "2025-03-01T15:00:00Z",
+            "last_activity": "2025-03-01T15:01:30Z",
+            "conversation_history": [],
+            "transcription_started": true,
+            "state": "listening",
+            "transcript": [
+                {"speaker": "bot", "source": "phrase", "text": "Buenas tardes", "confidence": null, "timestamp": "2025-03-01T15:00:05Z"},
+                {"speaker": "caller", "text": "Quiero una cita", "confidence": 0.9, "timestamp": "2025-03-01T15:01:30Z"}
+            ]
+        })).unwrap();
+
+        let now = chrono::DateTime::parse_from_rfc3339("2025-03-01T15:02:00Z").unwrap().with_timezone(&chrono::Utc);
+        let summary = CallSummary::from_session(&session, now);
+        assert_eq!(summary.state, CallState::Listening);
+        assert_eq!(summary.duration_secs, 120);
+        assert_eq!(summary.idle_secs, 30);
+        assert_eq!(summary.last_utterance.unwrap().text, "Quiero una cita");
+
+        assert_eq!(CallState::from_code("speaking"), Some(CallState::Speaking));
+        assert_eq!(CallState::from_code("hablando"), None);
+    }
+
+    #[test]
+    fn test_voice_profile_validation() {
+        use telnyx_ai_service::models::VoiceProfile;
+
+        assert!(VoiceProfile::default().validate().is_ok());
+        let edges = VoiceProfile { stability: Some(0.0), similarity_boost: Some(1.0), ..Default::default() };
+        assert!(edges.validate().is_ok());
+
+        let too_high = VoiceProfile { style: Some(1.5), ..Default::default() };
+        assert_eq!(too_high.validate().unwrap_err(), "voz.style must be between 0.0 and 1.0");
+        let negative = VoiceProfile { stability: Some(-0.1), ..Default::default() };
+        assert!(negative.validate().is_err());
+        let nan = VoiceProfile { similarity_boost: Some(f32::NAN), ..Default::default() };
+        assert!(nan.validate().is_err());
+    }
 }